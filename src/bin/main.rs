@@ -1,19 +1,68 @@
-use std::{env, time::Duration, process::exit};
+use std::{collections::BTreeSet, env, io::{self, Stdout}, path::Path, sync::mpsc::Receiver, time::{Duration, Instant}, process::exit};
 
-use burrito::burrito::{burrito_cfg::BurritoCfg, burrito_data::BurritoData, systems::{SystemContext, SystemMap, get_system_id}, log_watcher::{EventType, LogWatcher}};
-use burrito::burrito::systems;
-use burrito::burrito::alert;
+use chrono::{Local, Utc};
+use crossterm::{cursor, event::{self, Event, KeyEventKind}, terminal::{self, EnterAlternateScreen, LeaveAlternateScreen}};
+use ratatui::{backend::CrosstermBackend, Frame, Terminal};
+use burrito::burrito::{alert::{self, OutputFormat}, alert_rules::AlertAction, api::{ApiServer, ReaderInfo}, audio::{self, AudioEngine, Sound, SoundCache, SoundRequest}, burrito_cfg::BurritoCfg, cfg_check, coalesce::AlertCoalescer, commands::{self, Command}, control::{self, ControlServer}, dashboard::{ClientStatus, Dashboard, DashboardAction, DashboardNotifier, DashboardState}, diagnostics, escalation::ActiveAlerts, journal::{self, Journal, JournalQuery}, burrito_data::BurritoData, systems::{SystemContext, SystemMap, get_system_id}, log_watcher::{self, EventType, LogWatcher}, mining::MiningTracker, mutes::{Mute, Mutes, QuietHours}, ratting::{self, RattingTracker}, reload::{self, ConfigFile, ConfigWatcher}, tones::Tone};
+use strum::IntoEnumIterator;
+use burrito::burrito::{systems, utils};
+use burrito::burrito::notifier::Notifiers;
+
+/// Events kept in each tab of the dashboard
+const DASHBOARD_EVENTS: usize = 500;
 
 fn main() {
-    let args: Vec<String> = env::args().collect();
+    let mut args: Vec<String> = env::args().collect();
+    // Before anything is loaded, since loading stops at the first invalid file
+    if args.get(1..3) == Some(&["cfg".to_owned(), "check".to_owned()]) {
+        cli_cfg_check();
+    }
     let mut cfg = BurritoCfg::load_from_file();
+    if args.get(1).map(|arg| arg.as_str()) == Some("--output") {
+        cfg.output_format = args.get(2).and_then(|format| format.parse().ok()).unwrap_or_else(|| {
+            println!("--output requires `text` or `json`");
+            exit(1);
+        });
+        args.drain(1..3);
+    }
     let sys_map = systems::load_saved_system_map();
     let mut ctx = SystemContext::new(&sys_map);
-    if args.len() > 1 {
+    let tui = args.get(1).map(|arg| arg.as_str()) == Some("tui");
+    if args.len() > 1 && !tui {
         if args[1] == "cfg" {
             cli_cfg(args.into_iter().skip(2).collect(), &mut cfg, &mut ctx, &sys_map);
             exit(0);
         }
+        else if args[1] == "stats" {
+            cli_stats(args.into_iter().skip(2).collect(), &cfg, &BurritoData::load_from_file());
+            exit(0);
+        }
+        else if args[1] == "sound" {
+            cli_sound(args.into_iter().skip(2).collect(), &cfg);
+            exit(0);
+        }
+        else if args[1] == "history" {
+            cli_history(args.into_iter().skip(2).collect(), cfg.output_format);
+            exit(0);
+        }
+        else if args[1] == "mute" {
+            cli_mute(args.into_iter().skip(2).collect(), &cfg);
+            exit(0);
+        }
+        else if args[1] == "unmute" {
+            let mut mutes = Mutes::read().unwrap_or_else(|e| {
+                println!("{e}");
+                exit(1);
+            });
+            let count = mutes.remove(args.get(2).map(|category| category.as_str()));
+            mutes.save();
+            println!("Removed {count} mute(s)");
+            exit(0);
+        }
+        else if args[1] == "ctl" {
+            cli_ctl(args.into_iter().skip(2).collect());
+            exit(0);
+        }
         else if args[1] == "help" {
             print_help();
             exit(0);
@@ -31,67 +80,461 @@ fn main() {
     eprintln!("Burrito starting up");
     eprintln!("Setting current systems to {:?}", ctx.get_current_systems());
 
-    run_burrito(ctx, cfg, data, sys_map);
+    if tui {
+        run_dashboard(ctx, cfg, data, sys_map);
+    }
+    else {
+        run_burrito(ctx, cfg, data, sys_map);
+    }
 }
 
 fn run_burrito(ctx: SystemContext, cfg: BurritoCfg, data: BurritoData, sys_map: SystemMap) {
-    let mut log_watcher = LogWatcher::new(
-        ctx.clone(),
-        cfg.clone(),
-        data.clone(),
-        sys_map.clone(),
-    );
-    log_watcher.init();
+    let mut instance = Instance::new(ctx, cfg, data, sys_map, None);
+    let stdin_lines = commands::spawn_stdin_reader();
     eprintln!("Burrito ready!");
     loop {
-        log_watcher.get_events().into_iter().for_each(|event| {
+        while let Ok(line) = stdin_lines.try_recv() {
+            let result = Command::parse(&line).and_then(|command| instance.handle_command(command));
+            instance.report_result(result);
+        }
+        instance.run_commands();
+        instance.update();
+        std::thread::sleep(Duration::from_millis(instance.cfg.log_update_interval_ms))
+    }
+}
+
+/// Runs Burrito with the dashboard of `burrito tui` instead of printing events
+fn run_dashboard(ctx: SystemContext, cfg: BurritoCfg, data: BurritoData, sys_map: SystemMap) {
+    let mut instance = Instance::new(ctx, cfg, data, sys_map, Some(Dashboard::new(DASHBOARD_EVENTS)));
+    let result = start_terminal().and_then(|mut terminal| run_dashboard_loop(&mut instance, &mut terminal));
+    stop_terminal();
+    diagnostics::release();
+    if let Err(e) = result {
+        eprintln!("Dashboard failed: {e}");
+        exit(1);
+    }
+}
+
+fn run_dashboard_loop(instance: &mut Instance, terminal: &mut Terminal<CrosstermBackend<Stdout>>) -> io::Result<()> {
+    loop {
+        instance.run_commands();
+        instance.update();
+        terminal.draw(|frame| instance.draw_dashboard(frame))?;
+        let next_update = Instant::now() + Duration::from_millis(instance.cfg.log_update_interval_ms);
+        while let Some(timeout) = next_update.checked_duration_since(Instant::now()) {
+            if !event::poll(timeout)? {
+                break;
+            }
+            if let Event::Key(key) = event::read()? {
+                if key.kind != KeyEventKind::Press {
+                    continue;
+                }
+                let alert_ids: Vec<u32> = instance.active_alerts.get_active().iter().map(|active_alert| active_alert.id).collect();
+                match instance.dashboard.as_mut().and_then(|dashboard| dashboard.handle_key(key, &alert_ids)) {
+                    Some(DashboardAction::Quit) => return Ok(()),
+                    Some(DashboardAction::Run(command)) => {
+                        let result = instance.handle_command(command);
+                        instance.report_result(result);
+                    },
+                    None => {},
+                }
+            }
+            // Redraw after key presses and resizes
+            terminal.draw(|frame| instance.draw_dashboard(frame))?;
+        }
+    }
+}
+
+fn start_terminal() -> io::Result<Terminal<CrosstermBackend<Stdout>>> {
+    terminal::enable_raw_mode()?;
+    crossterm::execute!(io::stdout(), EnterAlternateScreen)?;
+    // Leave the dashboard before printing a panic, or it is unreadable
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        stop_terminal();
+        default_hook(info);
+    }));
+    Terminal::new(CrosstermBackend::new(io::stdout()))
+}
+
+fn stop_terminal() {
+    _ = terminal::disable_raw_mode();
+    _ = crossterm::execute!(io::stdout(), LeaveAlternateScreen, cursor::Show);
+}
+
+/// State of a running Burrito, kept between iterations of the main loop
+struct Instance {
+    cfg: BurritoCfg,
+    sys_map: SystemMap,
+    log_watcher: LogWatcher,
+    notifiers: Notifiers,
+    active_alerts: ActiveAlerts,
+    coalescer: AlertCoalescer,
+    mutes: Mutes,
+    journal: Option<Journal>,
+    api: Option<ApiServer>,
+    last_ratting_summary: Instant,
+    config_watcher: ConfigWatcher,
+    control: Option<ControlServer>,
+    /// Shows events and messages instead of printing them in `burrito tui`
+    dashboard: Option<Dashboard>,
+    /// Errors of notifiers and other background work, shown in the dashboard
+    diagnostics: Option<Receiver<String>>,
+}
+
+impl Instance {
+    fn new(ctx: SystemContext, cfg: BurritoCfg, data: BurritoData, sys_map: SystemMap, dashboard: Option<Dashboard>) -> Self {
+        let mut log_watcher = LogWatcher::new(
+            ctx,
+            cfg.clone(),
+            data,
+            sys_map.clone(),
+        );
+        log_watcher.init();
+        if let Err(e) = cfg.validate() {
+            eprintln!("{e}");
+        }
+        let api = if cfg.api_config.enabled {
+            let system_names = sys_map.get_systems().values().map(|system| system.name.to_owned()).collect();
+            match ApiServer::start(&cfg.api_config, system_names) {
+                Ok(api) => {
+                    eprintln!("API listening on http://{}/api", api.get_address());
+                    Some(api)
+                },
+                Err(e) => {
+                    eprintln!("{e}");
+                    None
+                },
+            }
+        }
+        else {
+            None
+        };
+        let control = match ControlServer::start() {
+            Ok(control) => Some(control),
+            Err(e) => {
+                eprintln!("{e}, `burrito ctl` will not reach this instance");
+                None
+            },
+        };
+        Self {
+            notifiers: get_notifiers(&cfg, dashboard.is_some()),
+            active_alerts: ActiveAlerts::new(),
+            coalescer: AlertCoalescer::new(cfg.alert_config.coalesce_window_ms),
+            mutes: Mutes::load_from_file(),
+            journal: open_journal(&cfg),
+            api,
+            last_ratting_summary: Instant::now(),
+            config_watcher: ConfigWatcher::open_default(),
+            control,
+            diagnostics: dashboard.is_some().then(diagnostics::capture),
+            dashboard,
+            cfg,
+            sys_map,
+            log_watcher,
+        }
+    }
+
+    /// Shows a message on stderr, or in the dashboard
+    fn report(&mut self, message: &str) {
+        match &mut self.dashboard {
+            Some(dashboard) => dashboard.add_message(message),
+            None => eprintln!("{message}"),
+        }
+    }
+
+    fn report_result(&mut self, result: Result<String, String>) {
+        match result {
+            Ok(message) | Err(message) => self.report(&message),
+        }
+    }
+
+    /// Runs the commands received by the API and the control socket
+    fn run_commands(&mut self) {
+        let commands = self.api.as_ref().map(|api| api.get_commands()).unwrap_or_default();
+        for command in commands {
+            let result = self.handle_command(command);
+            self.report_result(result);
+        }
+        let requests = self.control.as_ref().map(|control| control.get_requests()).unwrap_or_default();
+        for request in requests {
+            let result = self.handle_command(request.command.clone());
+            request.reply(result);
+        }
+    }
+
+    /// Reads the logs and runs the alerts that are due
+    fn update(&mut self) {
+        // Neither text nor JSON is printed while the dashboard is shown
+        let text = self.cfg.output_format == OutputFormat::Text && self.dashboard.is_none();
+        let json = self.cfg.output_format == OutputFormat::Json && self.dashboard.is_none();
+        if let Some(api) = &self.api {
+            api.update(self.log_watcher.get_context(), self.log_watcher.get_log_readers());
+        }
+        let messages: Vec<String> = self.diagnostics.as_ref().map(|receiver| receiver.try_iter().collect()).unwrap_or_default();
+        for message in messages {
+            self.report(&message);
+        }
+        // ctx.json is written whenever a path is cached, which is not an edit
+        self.config_watcher.set_loaded(ConfigFile::Context, self.log_watcher.get_context().get_modified());
+        for file in self.config_watcher.get_changed() {
+            match self.reload(file) {
+                Ok(changes) if changes.is_empty() => {},
+                Ok(changes) => {
+                    self.report(&format!("Reloaded {file}"));
+                    changes.iter().for_each(|change| self.report(&format!("\t{change}")));
+                },
+                Err(e) => self.report(&format!("{e}, keeping the previous {file}")),
+            }
+        }
+        if self.mutes.reload_if_changed() {
+            self.report("Mutes changed");
+            for line in get_mute_lines(&self.mutes, &self.cfg.quiet_hours) {
+                self.report(&line);
+            }
+        }
+        for event in self.log_watcher.get_events() {
+            if let Some(Err(e)) = self.journal.as_mut().map(|journal| journal.append(&event)) {
+                self.report(&e);
+            }
             match event.event_type {
                 EventType::ChatlogMessage => {
-                    if !cfg.hide_chat_messages {
+                    if !self.cfg.hide_chat_messages && text {
                         println!("{}", &event.trigger);
                     }
                 },
-                EventType::RangeOfSystem(event_distance) => {
-                    // TODO: change how alerts are handled so that this doesn't need to be two conditions
-                    if !cfg.hide_out_of_range_events {
+                EventType::RangeOfSystem(_) => {
+                    if !self.cfg.hide_out_of_range_events && text {
                         println!("{}", &event.trigger);
                     }
-                    for alert in &cfg.sound_config.audio_alerts {
-                        if let EventType::RangeOfSystem(alert_distance) = alert.trigger {
-                            if event_distance <= alert_distance {
-                                // TODO: change how alerts are handled so that this doesn't need to be two conditions
-                                if cfg.hide_out_of_range_events {
-                                    println!("{}", &event.trigger);
-                                }
-                                alert::alert(&event, &event.trigger, &event.character_name, Some(&alert.sound_file));
-                                break;
-                            }
-                        }
-                    }
                 },
-                EventType::FactionSpawn => {
-                    if let Some(audio_alert) = cfg.sound_config.audio_alerts.iter()
-                        .find(|a| a.trigger == event.event_type) {
-                        alert::alert(&event, &event.trigger, &event.character_name, Some(&audio_alert.sound_file))
-                    }
-                },
-                EventType::DreadSpawn => {
-                    if let Some(audio_alert) = cfg.sound_config.audio_alerts.iter()
-                        .find(|a| a.trigger == event.event_type) {
-                        alert::alert(&event, &event.trigger, &event.character_name, Some(&audio_alert.sound_file))
+                _ => {},
+            }
+            let actions = self.cfg.alert_config.get_actions(&event, &self.cfg.character_tags);
+            let alerted = !actions.is_empty()
+                && !self.mutes.is_muted(&event, &self.cfg.quiet_hours, Local::now())
+                && self.coalescer.add(&event, &actions, Utc::now());
+            if json {
+                alert::print_event_json(&event, alerted);
+            }
+            if let Some(api) = &self.api {
+                api.publish(&event, alerted);
+            }
+            if let Some(dashboard) = &mut self.dashboard {
+                dashboard.add_event(&event, alerted);
+            }
+            if alerted {
+                if let EventType::RangeOfSystem(_) = event.event_type {
+                    // Out of range events are hidden, but this one is in range
+                    if self.cfg.hide_out_of_range_events && text {
+                        println!("{}", &event.trigger);
                     }
-                },
-                EventType::OfficerSpawn => {
-                    if let Some(audio_alert) = cfg.sound_config.audio_alerts.iter()
-                        .find(|a| a.trigger == event.event_type) {
-                        alert::alert(&event, &event.trigger, &event.character_name, Some(&audio_alert.sound_file))
+                }
+                self.notifiers.notify(&event, &actions);
+                if let Some(policy) = self.cfg.alert_config.get_escalation(&event, &self.cfg.character_tags) {
+                    let id = self.active_alerts.add(event.clone(), actions, policy, Utc::now());
+                    match self.dashboard.is_some() {
+                        true => self.report(&format!("Alert {id} repeats until acknowledged")),
+                        false => self.report(&format!("Alert {id} repeats until acknowledged: press Enter to acknowledge all or type `ack {id}`")),
                     }
-                },
-                _ => {}// TODO: The rest of the events
+                }
             }
-        });
-        std::thread::sleep(Duration::from_millis(cfg.log_update_interval_ms))
+        }
+        if let Some((summary, actions)) = self.coalescer.get_summary(Utc::now()) {
+            if json {
+                alert::print_event_json(&summary, true);
+            }
+            if let Some(api) = &self.api {
+                api.publish(&summary, true);
+            }
+            if let Some(dashboard) = &mut self.dashboard {
+                dashboard.add_event(&summary, true);
+            }
+            self.notifiers.notify(&summary, &actions);
+        }
+        let (mutes, quiet_hours) = (&self.mutes, &self.cfg.quiet_hours);
+        let due = self.active_alerts.get_due(Utc::now(), |event| mutes.is_muted(event, quiet_hours, Local::now()));
+        for (active_alert, actions) in due {
+            // The dashboard shows how often each alert repeated
+            if self.dashboard.is_none() {
+                eprintln!("Repeating alert {} ({} time(s)), press Enter to acknowledge", active_alert.id, active_alert.repeats);
+            }
+            if json {
+                alert::print_event_json(&active_alert.event, true);
+            }
+            if let Some(api) = &self.api {
+                api.publish(&active_alert.event, true);
+            }
+            self.notifiers.notify(&active_alert.event, &actions);
+        }
+        let summary_interval_ms = self.cfg.ratting_config.summary_interval_ms;
+        if summary_interval_ms > 0 && self.last_ratting_summary.elapsed() >= Duration::from_millis(summary_interval_ms) {
+            for line in self.log_watcher.get_ratting_tracker().summary() {
+                if text {
+                    println!("{line}");
+                }
+                else {
+                    self.report(&line);
+                }
+            }
+            self.last_ratting_summary = Instant::now();
+        }
     }
+
+    /// Runs a command typed into the terminal or received from the API or control socket
+    fn handle_command(&mut self, command: Command) -> Result<String, String> {
+        match command {
+            Command::Acknowledge(id) => {
+                let count = self.active_alerts.acknowledge(id);
+                match id {
+                    Some(id) if count == 0 => Err(format!("No repeating alert with id {id}")),
+                    _ => Ok(format!("Acknowledged {count} alert(s)")),
+                }
+            },
+            Command::Mute(args) => {
+                let mute = Mute::parse(&args, Local::now())?;
+                let message = format!("Muted {mute}");
+                self.mutes.add(mute);
+                self.mutes.save();
+                Ok(message)
+            },
+            Command::Unmute(category) => {
+                let count = self.mutes.remove(category.as_deref());
+                self.mutes.save();
+                Ok(format!("Removed {count} mute(s)"))
+            },
+            Command::ListMutes => Ok(get_mute_lines(&self.mutes, &self.cfg.quiet_hours).join("\n")),
+            Command::WatchSystem(name) => {
+                if get_system_id(&name, &self.sys_map).is_none() {
+                    return Err(format!("Unknown system name: {name}"));
+                }
+                self.log_watcher.get_context_mut().watch_system(&name, &self.sys_map);
+                Ok(format!("Added {name} to system watch list"))
+            },
+            Command::UnwatchSystem(name) => {
+                self.log_watcher.get_context_mut().unwatch_system(&name, &self.sys_map);
+                Ok(format!("Removed {name} from system watch list"))
+            },
+            Command::WatchCharacter(name) => {
+                self.log_watcher.get_context_mut().watch_character(&name);
+                Ok(format!("Added {name} to character watch list"))
+            },
+            Command::UnwatchCharacter(name) => {
+                self.log_watcher.get_context_mut().unwatch_character(&name);
+                Ok(format!("Removed {name} from character watch list"))
+            },
+            Command::Reload => {
+                let mut lines = vec![];
+                let mut failed = false;
+                for file in ConfigFile::iter() {
+                    match self.reload(file) {
+                        Ok(changes) if changes.is_empty() => lines.push(format!("{file}: no changes")),
+                        Ok(changes) => {
+                            lines.push(format!("{file}:"));
+                            lines.extend(changes.iter().map(|change| format!("\t{change}")));
+                        },
+                        Err(e) => {
+                            failed = true;
+                            lines.push(format!("{e}, keeping the previous {file}"));
+                        },
+                    }
+                }
+                match failed {
+                    true => Err(lines.join("\n")),
+                    false => Ok(lines.join("\n")),
+                }
+            },
+            Command::ListReaders => {
+                let lines: Vec<String> = self.log_watcher.get_log_readers().iter()
+                    .map(ReaderInfo::from_reader)
+                    .map(|reader| match reader.chatlog {
+                        true => format!("{}: {} ({})", reader.character_name, reader.channel_name, reader.log_file),
+                        false => format!("{}: game log ({})", reader.character_name, reader.log_file),
+                    })
+                    .collect();
+                match lines.is_empty() {
+                    true => Ok("No logs are being read".to_owned()),
+                    false => Ok(lines.join("\n")),
+                }
+            },
+        }
+    }
+
+    fn draw_dashboard(&self, frame: &mut Frame) {
+        if let Some(dashboard) = &self.dashboard {
+            let ctx = self.log_watcher.get_context();
+            let readers: Vec<ReaderInfo> = self.log_watcher.get_log_readers().iter().map(ReaderInfo::from_reader).collect();
+            let mut watched_systems: Vec<String> = ctx.get_current_systems().iter().cloned().collect();
+            watched_systems.sort();
+            let mut watched_characters: Vec<String> = ctx.get_current_characters().iter().cloned().collect();
+            watched_characters.sort();
+            let state = DashboardState {
+                active_alerts: self.active_alerts.get_active(),
+                clients: ClientStatus::from_readers(&readers, self.log_watcher.get_activity_monitor()),
+                watched_systems,
+                watched_characters,
+            };
+            dashboard.draw(frame, &state, Utc::now());
+        }
+    }
+
+    /// Loads a config file again and describes what changed
+    ///
+    /// The new contents are checked before anything is replaced, so an invalid
+    /// file leaves the running config as it was.
+    fn reload(&mut self, file: ConfigFile) -> Result<Vec<String>, String> {
+        match file {
+            ConfigFile::Cfg => self.reload_config(),
+            ConfigFile::Data => {
+                let data = BurritoData::read_from_file()?;
+                let changes = reload::get_changes(self.log_watcher.get_data(), &data);
+                self.log_watcher.set_data(data);
+                Ok(changes)
+            },
+            ConfigFile::Context => {
+                let saved = systems::read_saved_context()?;
+                self.log_watcher.get_context_mut().set_watch_lists(&saved, &self.sys_map)
+            },
+        }
+    }
+
+    /// Replaces the config with burrito.cfg
+    ///
+    /// The output format stays the same, since whatever reads the output relies
+    /// on it. The API only picks up `api_config` on the next start.
+    fn reload_config(&mut self) -> Result<Vec<String>, String> {
+        let mut cfg = BurritoCfg::read_from_file()?;
+        cfg.validate()?;
+        cfg.output_format = self.cfg.output_format;
+        let mut changes = reload::get_changes(&self.cfg, &cfg);
+        if changes.is_empty() {
+            return Ok(changes);
+        }
+        if changes.iter().any(|change| change.starts_with("api_config")) {
+            changes.push("api_config changes take effect after a restart".to_owned());
+        }
+        self.notifiers = get_notifiers(&cfg, self.dashboard.is_some());
+        if cfg.alert_config.coalesce_window_ms != self.cfg.alert_config.coalesce_window_ms {
+            self.coalescer = AlertCoalescer::new(cfg.alert_config.coalesce_window_ms);
+        }
+        self.journal = open_journal(&cfg);
+        self.log_watcher.set_config(cfg.clone());
+        self.cfg = cfg;
+        Ok(changes)
+    }
+}
+
+/// Built-in notifiers, except that alerts are not printed over the dashboard
+fn get_notifiers(cfg: &BurritoCfg, dashboard: bool) -> Notifiers {
+    let mut notifiers = Notifiers::with_defaults(cfg);
+    if dashboard {
+        notifiers.register("terminal", Box::new(DashboardNotifier));
+    }
+    notifiers
+}
+
+fn open_journal(cfg: &BurritoCfg) -> Option<Journal> {
+    cfg.journal_config.enabled.then(|| Journal::open_default(cfg.journal_config.retention_days))
 }
 
 fn cli_cfg(args: Vec<String>, _cfg: &mut BurritoCfg, ctx: &mut SystemContext, sys_map: &SystemMap) {
@@ -110,7 +553,7 @@ fn cli_cfg(args: Vec<String>, _cfg: &mut BurritoCfg, ctx: &mut SystemContext, sy
                             match watch_type {
                                 "system" => {
                                     if let Some(_) = get_system_id(&name, sys_map) {
-                                        ctx.watch_system(&name);
+                                        ctx.watch_system(&name, sys_map);
                                     }
                                     else {
                                         println!("Unknown system name: {name}");
@@ -124,7 +567,7 @@ fn cli_cfg(args: Vec<String>, _cfg: &mut BurritoCfg, ctx: &mut SystemContext, sy
                         },
                         "unwatch" => {
                             match watch_type {
-                                "system" => ctx.unwatch_system(&name),
+                                "system" => ctx.unwatch_system(&name, sys_map),
                                 "character" => ctx.unwatch_character(&name),
                                 _ => panic!("Unreachable code"),
                             }
@@ -159,6 +602,234 @@ fn cli_cfg(args: Vec<String>, _cfg: &mut BurritoCfg, ctx: &mut SystemContext, sy
     }
 }
 
+fn cli_stats(args: Vec<String>, cfg: &BurritoCfg, data: &BurritoData) {
+    guard_arg_len(1, args.len(), "No statistics type specified");
+    let hours = match args.get(1) {
+        Some(hours) => hours.parse::<u64>().unwrap_or_else(|_| {
+            println!("Invalid number of hours: {hours}");
+            exit(1);
+        }),
+        None => 24,
+    };
+    let lines = log_watcher::read_game_log_history(&cfg.log_dir, hours * 3600).unwrap_or_else(|e| {
+        println!("{e}");
+        exit(1);
+    });
+    match args[0].as_str() {
+        "ratting" => {
+            let tracker = RattingTracker::from_game_log_lines(&lines);
+            if tracker.get_characters().is_empty() {
+                println!("No bounties found in the last {hours} hours");
+                return;
+            }
+            for character_name in tracker.get_characters() {
+                println!(
+                    "{}: {} ISK total, {} ISK/hour",
+                    character_name,
+                    ratting::format_isk(tracker.get_total_isk(&character_name)),
+                    ratting::format_isk_per_hour(tracker.get_isk_per_hour(&character_name)),
+                );
+                for session in tracker.get_sessions(&character_name) {
+                    println!(
+                        "\t{} - {} UTC: {} ticks, {} ISK, {} ISK/hour",
+                        session.first_tick.format("%Y.%m.%d %H:%M"),
+                        session.last_tick.format("%H:%M"),
+                        session.ticks,
+                        ratting::format_isk(session.total_isk),
+                        ratting::format_isk_per_hour(session.get_isk_per_hour()),
+                    );
+                }
+            }
+        },
+        "mining" => {
+            let tracker = MiningTracker::from_game_log_lines(&lines, data);
+            if tracker.get_characters().is_empty() {
+                println!("No mining found in the last {hours} hours");
+                return;
+            }
+            for character_name in tracker.get_characters() {
+                println!(
+                    "{}: {:.1} m3 total, {} m3/hour",
+                    character_name,
+                    tracker.get_total_m3(&character_name),
+                    format_m3_per_hour(tracker.get_m3_per_hour(&character_name)),
+                );
+                for ore_yield in tracker.get_ore_yields(&character_name) {
+                    println!(
+                        "\t{}: {} cycles, {} units, {} m3, {} m3/hour",
+                        ore_yield.ore,
+                        ore_yield.cycles,
+                        ore_yield.units,
+                        ore_yield.volume_m3.map(|v| format!("{:.1}", v)).unwrap_or("-".to_owned()),
+                        format_m3_per_hour(ore_yield.get_m3_per_hour()),
+                    );
+                }
+            }
+        },
+        stats_type => {
+            println!("Unrecognized statistics type: {stats_type}");
+            exit(1);
+        },
+    }
+}
+
+fn cli_sound(args: Vec<String>, cfg: &BurritoCfg) {
+    guard_arg_len(1, args.len(), "No sound command specified");
+    match args[0].as_str() {
+        "test" => {
+            guard_arg_len(2, args.len(), "No alert specified");
+            let alert = join_args(1, &args);
+            let rules = cfg.alert_config.find_rules(&alert);
+            let files: BTreeSet<String> = rules.iter()
+                .flat_map(|rule| rule.actions.iter())
+                .filter_map(|action| match action {
+                    AlertAction::Sound { file, .. } => Some(file.to_owned()),
+                    _ => None,
+                })
+                .collect();
+            let (sounds, errors) = SoundCache::load(&files);
+            errors.iter().for_each(|e| println!("{e}"));
+            let mut requests = vec![];
+            for rule in rules {
+                for action in &rule.actions {
+                    let (sound, volume, priority) = match action {
+                        AlertAction::Sound { file, volume, priority } if sounds.contains(file) => (Sound::File(file.to_owned()), volume, priority),
+                        // Same fallback as when an alert fires
+                        AlertAction::Sound { volume, priority, .. } => match rule.condition.event_types.first() {
+                            Some(kind) => (Sound::Tone(Tone::for_event_kind(kind)), volume, priority),
+                            None => continue,
+                        },
+                        AlertAction::Tone { tone, volume, priority } => (Sound::Tone(*tone), volume, priority),
+                        _ => continue,
+                    };
+                    requests.push(SoundRequest { sound, volume: *volume, priority: *priority });
+                }
+            }
+            if requests.is_empty() {
+                println!("No alert rule named {alert} or for event type {alert} plays a sound");
+                exit(1);
+            }
+            let engine = AudioEngine::new(&cfg.sound_config, sounds);
+            for request in requests {
+                println!("Playing {}", request.sound);
+                _ = engine.play(request);
+            }
+            engine.finish();
+        },
+        "devices" => {
+            let names = audio::get_device_names().unwrap_or_else(|e| {
+                println!("Unable to list audio devices: {e}");
+                exit(1);
+            });
+            if names.is_empty() {
+                println!("No audio output devices found");
+                return;
+            }
+            let default = audio::get_default_device_name();
+            let selected = cfg.sound_config.audio_device.as_deref().and_then(|wanted| audio::select_device(&names, wanted));
+            for (i, name) in names.iter().enumerate() {
+                let mut notes = vec![];
+                if Some(name) == default.as_ref() {
+                    notes.push("default");
+                }
+                if Some(i) == selected {
+                    notes.push("selected by audio_device");
+                }
+                if notes.is_empty() {
+                    println!("{name}");
+                }
+                else {
+                    println!("{name} ({})", notes.join(", "));
+                }
+            }
+        },
+        cmd => {
+            println!("Unrecognized sound command: {cmd}");
+            exit(1);
+        },
+    }
+}
+
+fn cli_history(args: Vec<String>, format: OutputFormat) {
+    let query = JournalQuery::parse(&args, Utc::now()).unwrap_or_else(|e| {
+        println!("{e}");
+        exit(1);
+    });
+    let events = query.run(&journal::get_default_dir());
+    if events.is_empty() && format == OutputFormat::Text {
+        println!("No events found");
+        return;
+    }
+    for event in events {
+        if format == OutputFormat::Json {
+            println!("{}", alert::get_event_json(&event));
+            continue;
+        }
+        println!("{} {}", event.time.format("%Y.%m.%d %H:%M:%S"), alert::format_event(&event));
+        // Intel events only show the distance, the report itself says who was where
+        if event.event_type.get_distance().is_some() {
+            println!("\t{}", event.trigger);
+        }
+    }
+}
+
+fn cli_mute(args: Vec<String>, cfg: &BurritoCfg) {
+    let mut mutes = Mutes::read().unwrap_or_else(|e| {
+        println!("{e}");
+        exit(1);
+    });
+    if args.first().map(|arg| arg.as_str()) == Some("list") {
+        get_mute_lines(&mutes, &cfg.quiet_hours).iter().for_each(|line| println!("{line}"));
+        return;
+    }
+    let mute = Mute::parse(&args, Local::now()).unwrap_or_else(|e| {
+        println!("{e}");
+        exit(1);
+    });
+    println!("Muted {mute}");
+    mutes.add(mute);
+    mutes.save();
+}
+
+/// Prints every problem in the files in `.burrito/`, exits with 1 if there are any
+fn cli_cfg_check() -> ! {
+    let dir = utils::get_burrito_dir();
+    let problems = cfg_check::check_dir(Path::new(&dir));
+    if problems.is_empty() {
+        println!("No problems found in {dir}");
+        exit(0);
+    }
+    for problem in &problems {
+        println!("{problem}");
+    }
+    println!("{} problem(s) found in {dir}", problems.len());
+    exit(1);
+}
+
+fn cli_ctl(args: Vec<String>) {
+    guard_arg_len(1, args.len(), "No command specified, e.g. `burrito ctl watch system UALX-3`");
+    match control::send_command(&control::get_default_address(), &join_args(0, &args)) {
+        Ok(message) => println!("{message}"),
+        Err(e) => {
+            println!("{e}");
+            exit(1);
+        },
+    }
+}
+
+fn get_mute_lines(mutes: &Mutes, quiet_hours: &[QuietHours]) -> Vec<String> {
+    let mut lines: Vec<String> = mutes.get_active(Utc::now()).iter().map(|mute| format!("Muted {mute}")).collect();
+    lines.extend(quiet_hours.iter().map(|quiet_hours| format!("Quiet hours: {quiet_hours}")));
+    if lines.is_empty() {
+        lines.push("Nothing is muted".to_owned());
+    }
+    lines
+}
+
+fn format_m3_per_hour(m3_per_hour: Option<f64>) -> String {
+    m3_per_hour.map(|v| format!("{:.1}", v)).unwrap_or("-".to_owned())
+}
+
 fn guard_arg_len(minimum: usize, actual: usize, message: impl ToString) {
     let message = message.to_string();
     if actual < minimum {
@@ -186,7 +857,23 @@ fn print_help() {
         Examples:
         `burrito`\t\t\tRuns burrito
         `burrito help`\t\t\tPrints this output
+        `burrito tui`\t\t\tRuns burrito with a dashboard of intel, alerts, spawns, nearby systems and clients
+        `burrito --output json`\t\tRuns burrito, printing one JSON object per event
         `burrito cfg watch system UALX-3`\tAdds UALX-3 to system watch list
         `burrito cfg unwatch system UALX-3`\tRemoves UALX-3 from system watch list
+        `burrito cfg check`\t\t\tLists problems in burrito.cfg, burrito.dat, ctx.json and the other config files
+        `burrito stats ratting [hours]`\tShows bounty income per character from the last 24 (or [hours]) hours of game logs
+        `burrito stats mining [hours]`\tShows mining yield per character and ore type
+        `burrito sound devices`\t\tLists audio output devices
+        `burrito sound test FactionSpawn`\tPlays the sounds of the alert rules named or for the event type FactionSpawn
+        `burrito history --since 2h --system UALX-3`\tShows the events involving UALX-3 from the last 2 hours
+        `burrito mute spawn 30m`\t\tMutes spawn alerts for 30 minutes
+        `burrito mute intel beyond 2 until 08:00`\tOnly alerts on intel within 2 jumps until 08:00
+        `burrito mute list`\t\tLists mutes and quiet hours
+        `burrito unmute [category]`\t\tRemoves all mutes, or the mutes for a category
+        `burrito ctl watch system UALX-3`\tAdds UALX-3 to the watch list of the running Burrito
+        `burrito ctl mute spawn 30m`\t\tMutes spawn alerts of the running Burrito for 30 minutes
+        `burrito ctl reload`\t\tReloads the config files in the running Burrito and prints what changed
+        `burrito ctl readers`\t\tLists the logs the running Burrito reads
     ");
 }
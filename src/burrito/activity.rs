@@ -0,0 +1,103 @@
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, Utc};
+
+use super::{burrito_data::BurritoData, utils};
+
+#[derive(Clone, Debug)]
+struct ClientActivity {
+    last_activity: DateTime<Utc>,
+    in_space: bool,
+    inactive_alerted: bool,
+}
+
+/// Tracks game log activity per character to notice clients that silently died
+///
+/// Clients that crash mostly just stop writing to their game log. Whether a
+/// character is in space is guessed from its game log: combat, mining, bounty
+/// and undock/jump lines put it in space, docking lines take it out.
+#[derive(Clone, Debug, Default)]
+pub struct ActivityMonitor {
+    clients: BTreeMap<String, ClientActivity>,
+}
+
+impl ActivityMonitor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records a game log line written by a character's client
+    pub fn record_game_log_line(&mut self, character_name: &str, time: DateTime<Utc>, msg_type: &str, content: &str, data: &BurritoData) {
+        let client = self.clients.entry(character_name.to_owned()).or_insert(ClientActivity {
+            last_activity: time,
+            in_space: false,
+            inactive_alerted: false,
+        });
+        client.last_activity = time;
+        client.inactive_alerted = false;
+        let content = utils::strip_tags(content);
+        if data.docked_messages.iter().any(|m| content.contains(m.as_str())) {
+            client.in_space = false;
+        }
+        else if matches!(msg_type, "combat" | "mining" | "bounty")
+            || data.in_space_messages.iter().any(|m| content.contains(m.as_str())) {
+            client.in_space = true;
+        }
+    }
+
+    /// Forgets a character's state, e.g. when its client started a new session
+    pub fn reset(&mut self, character_name: &str) {
+        self.clients.remove(character_name);
+    }
+
+    pub fn is_in_space(&self, character_name: &str) -> bool {
+        self.clients.get(character_name).map(|c| c.in_space).unwrap_or(false)
+    }
+
+    /// Time of the last game log line of a character
+    pub fn get_last_activity(&self, character_name: &str) -> Option<DateTime<Utc>> {
+        self.clients.get(character_name).map(|c| c.last_activity)
+    }
+
+    /// Returns the characters in space whose game log has been quiet for `inactive_ms`
+    ///
+    /// Each character is only returned once until its client writes again.
+    pub fn check_inactive(&mut self, now: DateTime<Utc>, inactive_ms: u64) -> Vec<String> {
+        let mut inactive = vec![];
+        for (character_name, client) in self.clients.iter_mut() {
+            if !client.in_space || client.inactive_alerted {
+                continue;
+            }
+            if (now - client.last_activity).num_milliseconds() >= inactive_ms as i64 {
+                client.inactive_alerted = true;
+                inactive.push(character_name.to_owned());
+            }
+        }
+        inactive
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{TimeZone, Utc};
+
+    use crate::burrito::burrito_data::BurritoData;
+
+    use super::ActivityMonitor;
+
+    #[test]
+    fn test_inactive_clients() {
+        let data = BurritoData::default();
+        let mut uut = ActivityMonitor::new();
+        let start = Utc.timestamp_millis_opt(0).unwrap();
+        let later = Utc.timestamp_millis_opt(600_000).unwrap();
+        uut.record_game_log_line("Docked Alt", start, "notify", "Requested to dock at (station)", &data);
+        uut.record_game_log_line("Ratting Alt", start, "combat", "523 from Dire Pithi Arrogator - Hits", &data);
+        assert!(uut.is_in_space("Ratting Alt"));
+        assert!(!uut.is_in_space("Docked Alt"));
+        assert_eq!(vec!["Ratting Alt".to_owned()], uut.check_inactive(later, 600_000));
+        assert!(uut.check_inactive(later, 600_000).is_empty());
+        uut.record_game_log_line("Ratting Alt", later, "combat", "12 from Dire Pithi Arrogator - Grazes", &data);
+        assert!(uut.check_inactive(later, 600_000).is_empty());
+    }
+}
@@ -1,45 +1,96 @@
-use std::{io::{BufReader, Write}, fs::File, thread};
-use rodio::{Decoder, OutputStream, Sink};
+use std::io::Write;
+use serde_derive::{Deserialize, Serialize};
+use strum_macros::{Display, EnumString};
 use termcolor::{StandardStream, ColorSpec, WriteColor};
 
 use super::log_watcher::{EventType, LogEvent};
 
-fn play_file(path: String) {// TODO: Remove panics
-    thread::spawn(move || {
-        let (_stream, stream_handle) = OutputStream::try_default().unwrap();
-        let sink = Sink::try_new(&stream_handle).unwrap();
-        let file = BufReader::new(File::open(path).unwrap());
-        let source = Decoder::new(file).unwrap();
-        sink.append(source);
-        sink.sleep_until_end();
-    });
+pub fn print_event(event: &LogEvent) {
+    let mut stdout = StandardStream::stdout(termcolor::ColorChoice::Auto);
+    _ = stdout.set_color(&get_color_spec(&event.event_type));
+    _ = write!(&mut stdout, "{}", format_event(event));
+    _ = stdout.set_color(ColorSpec::new().set_fg(None).set_bg(None).set_bold(false));
+    _ = writeln!(&mut stdout);
 }
 
-pub fn alert(event: &LogEvent, trigger: &str, character_or_system_name: &str, sound_file: Option<&str>) {
-    let event_type = event.event_type.to_owned();
-    let mut stdout = StandardStream::stdout(termcolor::ColorChoice::Auto);
-    _ = stdout.set_color(&get_color_spec(&event_type));
-    match event_type {
-        EventType::RangeOfSystem(_) => {
-            _ = write!(&mut stdout, "{}", event.message);
+/// Text shown for an event when it triggers an alert
+pub fn format_event(event: &LogEvent) -> String {
+    match event.event_type {
+        EventType::RangeOfSystem(_) | EventType::RangeOfCharacter(_)
+        | EventType::SystemClear(_) | EventType::SystemStatusRequest(_) => {
+            event.message.to_owned()
+        },
+        EventType::ChatlogMessage => {
+            event.trigger.to_owned()
         },
-        EventType::FactionSpawn | EventType::DreadSpawn | EventType::OfficerSpawn => {
-            _ = write!(&mut stdout, "[{}] {}", character_or_system_name, trigger);
+        EventType::GamelogMessage | EventType::FactionSpawn | EventType::DreadSpawn
+        | EventType::TitanSpawn | EventType::OfficerSpawn => {
+            format!("[{}] {}", event.character_name, event.trigger)
         },
-        _ => {
-            // TODO: Everything else
+        EventType::SystemChangedMessage | EventType::ChatConnectionLost | EventType::ChatConnectionRestored
+        | EventType::Bounty | EventType::BountyTicksStopped | EventType::MiningYield
+        | EventType::OreHoldFull | EventType::AsteroidDepleted | EventType::DamageThreshold(_)
+        | EventType::ClientInactive | EventType::SessionEnded => {
+            format!("[{}] {}", event.character_name, event.message)
         },
     }
-    if let Some(filename) = sound_file {
-        play_file(filename.to_owned());
+}
+
+/// Named fields of an event for use outside of Burrito, e.g. in webhook payloads
+///
+/// Fields that do not apply to the event are empty.
+pub fn get_event_fields(event: &LogEvent) -> Vec<(&'static str, String)> {
+    vec![
+        ("time", event.time.to_rfc3339()),
+        ("character_name", event.character_name.to_owned()),
+        ("event_type", event.event_type.get_kind().to_string()),
+        ("trigger", event.trigger.to_owned()),
+        ("message", event.message.to_owned()),
+        ("text", format_event(event)),
+        ("distance", event.event_type.get_distance().map(|d| d.to_string()).unwrap_or_default()),
+        ("channel", event.details.channel.to_owned()),
+        ("reporter", event.details.reporter.to_owned()),
+        ("system", event.details.reported_system.to_owned()),
+        ("watched_system", event.details.watched_system.to_owned()),
+    ]
+}
+
+/// `get_event_fields` as a JSON object, with the distance as a number
+pub fn get_event_json(event: &LogEvent) -> serde_json::Value {
+    let mut object = serde_json::Map::new();
+    for (name, value) in get_event_fields(event) {
+        object.insert(name.to_owned(), value.into());
     }
-    _ = stdout.set_color(ColorSpec::new().set_fg(None).set_bg(None).set_bold(false));
-    _ = writeln!(&mut stdout, "");
+    object.insert("distance".to_owned(), event.event_type.get_distance().into());
+    serde_json::Value::Object(object)
+}
+
+/// `get_event_json` with whether the event triggered an alert
+pub fn get_alert_json(event: &LogEvent, alert: bool) -> serde_json::Value {
+    let mut json = get_event_json(event);
+    json["alert"] = alert.into();
+    json
+}
+
+/// Prints an event as one line of JSON, with whether it triggered an alert
+pub fn print_event_json(event: &LogEvent, alert: bool) {
+    println!("{}", get_alert_json(event, alert));
+}
+
+/// How Burrito prints events on stdout
+#[derive(Clone, Copy, Debug, Default, Deserialize, Display, EnumString, Eq, PartialEq, Serialize)]
+#[strum(ascii_case_insensitive)]
+pub enum OutputFormat {
+    /// Coloured text for people
+    #[default]
+    Text,
+    /// One JSON object per event and line, from `get_event_json`
+    Json,
 }
 
 fn get_color_spec(event_type: &EventType) -> ColorSpec {
     match event_type {
-        EventType::RangeOfSystem(_) => {
+        EventType::RangeOfSystem(_) | EventType::RangeOfCharacter(_) => {
             ColorSpec::new()
                 .set_bg(None)
                 .set_fg(Some(termcolor::Color::Red))
@@ -54,7 +105,21 @@ fn get_color_spec(event_type: &EventType) -> ColorSpec {
                 .set_bold(true)
                 .to_owned()
         },
-        EventType::DreadSpawn => {
+        EventType::SystemClear(_) => {
+            ColorSpec::new()
+                .set_bg(None)
+                .set_fg(Some(termcolor::Color::Green))
+                .set_bold(true)
+                .to_owned()
+        },
+        EventType::SystemStatusRequest(_) => {
+            ColorSpec::new()
+                .set_bg(None)
+                .set_fg(Some(termcolor::Color::Cyan))
+                .set_bold(true)
+                .to_owned()
+        },
+        EventType::DreadSpawn | EventType::TitanSpawn => {
             ColorSpec::new()
                 .set_bg(Some(termcolor::Color::Red))
                 .set_fg(Some(termcolor::Color::White))
@@ -68,7 +133,36 @@ fn get_color_spec(event_type: &EventType) -> ColorSpec {
                 .set_bold(true)
                 .to_owned()
         },
-        _ => {
+        EventType::DamageThreshold(_) => {
+            ColorSpec::new()
+                .set_bg(Some(termcolor::Color::Red))
+                .set_fg(Some(termcolor::Color::Yellow))
+                .set_bold(true)
+                .to_owned()
+        },
+        EventType::ChatConnectionLost | EventType::ClientInactive | EventType::SessionEnded => {
+            ColorSpec::new()
+                .set_bg(Some(termcolor::Color::Yellow))
+                .set_fg(Some(termcolor::Color::Black))
+                .set_bold(true)
+                .to_owned()
+        },
+        EventType::ChatConnectionRestored | EventType::Bounty => {
+            ColorSpec::new()
+                .set_bg(None)
+                .set_fg(Some(termcolor::Color::Green))
+                .set_bold(true)
+                .to_owned()
+        },
+        EventType::BountyTicksStopped | EventType::OreHoldFull | EventType::AsteroidDepleted => {
+            ColorSpec::new()
+                .set_bg(None)
+                .set_fg(Some(termcolor::Color::Yellow))
+                .set_bold(true)
+                .to_owned()
+        },
+        EventType::ChatlogMessage | EventType::GamelogMessage | EventType::SystemChangedMessage
+        | EventType::MiningYield => {
             ColorSpec::new().set_fg(None).set_bg(None).set_bold(false).to_owned()
         },
     }
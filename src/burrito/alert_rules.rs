@@ -0,0 +1,569 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use serde_derive::{Deserialize, Serialize};
+
+use super::{burrito_cfg::AudioAlert, log_watcher::{EventKind, EventType, LogEvent}, tones::Tone};
+
+/// Alert rules and how they are combined when several match an event
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct AlertConfig {
+    #[serde(default)]
+    pub strategy: AlertStrategy,
+    #[serde(default)]
+    pub rules: Vec<AlertRule>,
+    /// Hostile reports that arrive within this many milliseconds of each other
+    /// are merged into one alert, 0 alerts on every report
+    #[serde(default)]
+    pub coalesce_window_ms: u64,
+    /// Set when `alert_config` was missing from `burrito.cfg`, meaning the rules
+    /// still have to be created from the old `audio_alerts`
+    #[serde(skip)]
+    pub needs_migration: bool,
+}
+
+impl AlertConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        for rule in &self.rules {
+            if let Some(policy) = &rule.escalation {
+                policy.validate().map_err(|e| format!("Invalid escalation of alert rule {:?}: {e}", rule.name))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Value used when `alert_config` is missing from `burrito.cfg`
+    pub fn missing() -> Self {
+        Self {
+            needs_migration: true,
+            ..Default::default()
+        }
+    }
+
+    /// Builds rules equivalent to the old `audio_alerts` configuration
+    ///
+    /// Every audio alert becomes a rule that prints the event and plays its sound.
+    /// `RangeOfSystem(n)` alerts become rules for events within `n` jumps. Event
+    /// types without an audio alert get a rule that plays a generated tone.
+    pub fn from_audio_alerts<'a>(audio_alerts: impl IntoIterator<Item = &'a AudioAlert>) -> Self {
+        let mut rules: Vec<AlertRule> = audio_alerts.into_iter().map(|audio_alert| {
+            let mut condition = AlertCondition {
+                event_types: vec![audio_alert.trigger.get_kind()],
+                ..Default::default()
+            };
+            if let Some(d) = audio_alert.trigger.get_distance() {
+                condition.distance = Some(DistanceCondition { comparison: Comparison::LessOrEqual, jumps: d });
+            }
+            if let EventType::DamageThreshold(dps) = audio_alert.trigger {
+                condition.damage_threshold = Some(dps);
+            }
+            AlertRule {
+                name: format!("{:?}", audio_alert.trigger),
+                condition,
+                actions: vec![AlertAction::Print, AlertAction::sound(&audio_alert.sound_file)],
+                priority: 0,
+                stop_processing: false,
+                escalation: None,
+            }
+        }).collect();
+        let mut tone_kinds = vec![
+            EventKind::BountyTicksStopped,
+            EventKind::OreHoldFull,
+            EventKind::AsteroidDepleted,
+            EventKind::DamageThreshold,
+            EventKind::ChatConnectionLost,
+            EventKind::ChatConnectionRestored,
+            EventKind::ClientInactive,
+            EventKind::SessionEnded,
+        ];
+        tone_kinds.retain(|kind| !rules.iter().any(|r| r.condition.event_types.contains(kind)));
+        rules.extend(tone_kinds.into_iter().map(|kind| AlertRule {
+            name: kind.to_string(),
+            condition: AlertCondition { event_types: vec![kind], ..Default::default() },
+            actions: vec![AlertAction::Print, AlertAction::tone(Tone::for_event_kind(&kind))],
+            priority: 0,
+            stop_processing: false,
+            escalation: None,
+        }));
+        // Clear and status messages are only interesting close to home
+        for kind in [EventKind::SystemClear, EventKind::SystemStatusRequest] {
+            if rules.iter().any(|r| r.condition.event_types.contains(&kind)) {
+                continue;
+            }
+            rules.push(AlertRule {
+                name: kind.to_string(),
+                condition: AlertCondition {
+                    event_types: vec![kind],
+                    distance: Some(DistanceCondition { comparison: Comparison::LessOrEqual, jumps: 5 }),
+                    ..Default::default()
+                },
+                actions: vec![AlertAction::Print, AlertAction::tone(Tone::for_event_kind(&kind))],
+                priority: 0,
+                stop_processing: false,
+                escalation: None,
+            });
+        }
+        Self {
+            strategy: AlertStrategy::NearestRange,
+            rules,
+            coalesce_window_ms: 0,
+            needs_migration: false,
+        }
+    }
+
+    /// Every sound file played by a rule
+    pub fn get_sound_files(&self) -> BTreeSet<String> {
+        self.rules.iter()
+            .flat_map(|rule| rule.actions.iter())
+            .filter_map(|action| match action {
+                AlertAction::Sound { file, .. } => Some(file.to_owned()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Rules with the name `alert`, or if there are none, the rules for the event type `alert`
+    ///
+    /// Names and event types are not case-sensitive.
+    pub fn find_rules(&self, alert: &str) -> Vec<&AlertRule> {
+        let named: Vec<&AlertRule> = self.rules.iter()
+            .filter(|rule| rule.name.eq_ignore_ascii_case(alert))
+            .collect();
+        if !named.is_empty() {
+            return named;
+        }
+        self.rules.iter()
+            .filter(|rule| rule.condition.event_types.iter().any(|kind| kind.to_string().eq_ignore_ascii_case(alert)))
+            .collect()
+    }
+
+    /// Returns the actions to run for an event
+    ///
+    /// Duplicate actions of the rules from `get_rules` are only returned once.
+    pub fn get_actions(&self, event: &LogEvent, character_tags: &BTreeMap<String, BTreeSet<String>>) -> Vec<AlertAction> {
+        let mut actions: Vec<AlertAction> = vec![];
+        for action in self.get_rules(event, character_tags).into_iter().flat_map(|rule| rule.actions.iter()) {
+            if !actions.contains(action) {
+                actions.push(action.clone());
+            }
+        }
+        actions
+    }
+
+    /// Escalation policy of the first rule from `get_rules` that has one
+    pub fn get_escalation(&self, event: &LogEvent, character_tags: &BTreeMap<String, BTreeSet<String>>) -> Option<EscalationPolicy> {
+        self.get_rules(event, character_tags).into_iter().find_map(|rule| rule.escalation.clone())
+    }
+
+    /// Returns the rules that run for an event
+    ///
+    /// Rules are checked from highest to lowest priority. A matching rule with
+    /// `stop_processing` set ends the search. The strategy then decides which of
+    /// the matching rules run.
+    pub fn get_rules(&self, event: &LogEvent, character_tags: &BTreeMap<String, BTreeSet<String>>) -> Vec<&AlertRule> {
+        let mut rules: Vec<&AlertRule> = self.rules.iter().collect();
+        rules.sort_by(|a, b| b.priority.cmp(&a.priority));
+        let mut matching: Vec<&AlertRule> = vec![];
+        for rule in rules {
+            if !rule.condition.matches(event, character_tags) {
+                continue;
+            }
+            matching.push(rule);
+            if rule.stop_processing || self.strategy == AlertStrategy::FirstMatch {
+                break;
+            }
+        }
+        if self.strategy == AlertStrategy::NearestRange {
+            let nearest = matching.iter()
+                .filter_map(|rule| rule.condition.distance.as_ref())
+                .map(|distance| distance.jumps)
+                .min();
+            if let Some(nearest) = nearest {
+                let mut found = false;
+                matching.retain(|rule| {
+                    match &rule.condition.distance {
+                        Some(distance) if distance.jumps == nearest && !found => {
+                            found = true;
+                            true
+                        },
+                        Some(_) => false,
+                        None => true,
+                    }
+                });
+            }
+        }
+        matching
+    }
+}
+
+impl Default for AlertConfig {
+    fn default() -> Self {
+        Self {
+            strategy: AlertStrategy::NearestRange,
+            rules: vec![],
+            coalesce_window_ms: 0,
+            needs_migration: false,
+        }
+    }
+}
+
+/// How matching rules are combined
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub enum AlertStrategy {
+    /// Every matching rule runs, except that of the rules with a distance
+    /// condition only the one with the smallest number of jumps runs
+    ///
+    /// With alerts for 5 and 10 jumps, an event 3 jumps away only triggers the
+    /// 5 jump alert.
+    #[default]
+    NearestRange,
+    /// Only the first matching rule runs
+    FirstMatch,
+    /// Every matching rule runs
+    AllMatches,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct AlertRule {
+    #[serde(default)]
+    pub name: String,
+    #[serde(default)]
+    pub condition: AlertCondition,
+    #[serde(default)]
+    pub actions: Vec<AlertAction>,
+    /// Rules with a higher priority are checked first
+    #[serde(default)]
+    pub priority: u8,
+    /// Don't check any further rules if this one matches
+    #[serde(default)]
+    pub stop_processing: bool,
+    /// Repeat the actions until the alert is acknowledged
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub escalation: Option<EscalationPolicy>,
+}
+
+/// How an alert repeats until it is acknowledged
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct EscalationPolicy {
+    /// Time between repeats
+    pub repeat_interval_ms: u64,
+    /// Number of repeats after which `escalation_actions` run instead of the
+    /// rule's actions (0 never escalates)
+    #[serde(default)]
+    pub escalate_after: u32,
+    #[serde(default)]
+    pub escalation_actions: Vec<AlertAction>,
+    /// Number of repeats after which the alert gives up (0 repeats until acknowledged)
+    #[serde(default)]
+    pub max_repeats: u32,
+}
+
+impl EscalationPolicy {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.repeat_interval_ms == 0 {
+            return Err("repeat_interval_ms must be greater than 0".to_owned());
+        }
+        Ok(())
+    }
+}
+
+/// Condition for an alert rule
+///
+/// Every field that is set must match. Empty lists match anything, otherwise
+/// one of the listed values must match.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct AlertCondition {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub event_types: Vec<EventKind>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub distance: Option<DistanceCondition>,
+    /// Threshold of a `DamageThreshold` event
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub damage_threshold: Option<u32>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub channels: Vec<String>,
+    /// Character whose client produced the event
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub characters: Vec<String>,
+    /// Tags from `character_tags` of the character whose client produced the event
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub character_tags: Vec<String>,
+    /// System named in an intel report
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub systems: Vec<String>,
+    /// Watched system an intel report was measured from
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub watched_systems: Vec<String>,
+    /// Character that posted an intel report
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub reporters: Vec<String>,
+    /// Words that must appear in the log line (case-insensitive)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub keywords: Vec<String>,
+}
+
+impl AlertCondition {
+    pub fn matches(&self, event: &LogEvent, character_tags: &BTreeMap<String, BTreeSet<String>>) -> bool {
+        if !self.event_types.is_empty() && !self.event_types.contains(&event.event_type.get_kind()) {
+            return false;
+        }
+        if let Some(distance) = &self.distance {
+            match event.event_type.get_distance() {
+                Some(d) if distance.matches(d) => {},
+                _ => return false,
+            }
+        }
+        if let Some(threshold) = self.damage_threshold {
+            if event.event_type != EventType::DamageThreshold(threshold) {
+                return false;
+            }
+        }
+        if !matches_any(&self.channels, &event.details.channel)
+            || !matches_any(&self.characters, &event.character_name)
+            || !matches_any(&self.systems, &event.details.reported_system)
+            || !matches_any(&self.watched_systems, &event.details.watched_system)
+            || !matches_any(&self.reporters, &event.details.reporter) {
+            return false;
+        }
+        if !self.character_tags.is_empty() {
+            let tagged = character_tags.get(&event.character_name)
+                .map(|tags| self.character_tags.iter().any(|t| tags.contains(t)))
+                .unwrap_or(false);
+            if !tagged {
+                return false;
+            }
+        }
+        if !self.keywords.is_empty() {
+            let text = event.trigger.to_lowercase();
+            if !self.keywords.iter().any(|k| text.contains(&k.to_lowercase())) {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+fn matches_any(values: &[String], value: &str) -> bool {
+    values.is_empty() || values.iter().any(|v| v.eq_ignore_ascii_case(value))
+}
+
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct DistanceCondition {
+    pub comparison: Comparison,
+    pub jumps: u32,
+}
+
+impl DistanceCondition {
+    pub fn matches(&self, distance: u32) -> bool {
+        match self.comparison {
+            Comparison::Less => distance < self.jumps,
+            Comparison::LessOrEqual => distance <= self.jumps,
+            Comparison::Equal => distance == self.jumps,
+            Comparison::GreaterOrEqual => distance >= self.jumps,
+            Comparison::Greater => distance > self.jumps,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub enum Comparison {
+    Less,
+    LessOrEqual,
+    Equal,
+    GreaterOrEqual,
+    Greater,
+}
+
+/// What to do when a rule matches
+#[derive(Clone, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
+pub enum AlertAction {
+    /// Print the event to the terminal
+    Print,
+    /// Play a sound file
+    Sound {
+        file: String,
+        /// Volume in percent of the file's own volume
+        #[serde(default = "default_volume")]
+        volume: u32,
+        /// Sounds with a higher priority play first and interrupt lower priority sounds
+        #[serde(default)]
+        priority: u8,
+    },
+    /// Play a tone that Burrito generates itself
+    Tone {
+        tone: Tone,
+        /// Volume in percent
+        #[serde(default = "default_volume")]
+        volume: u32,
+        #[serde(default)]
+        priority: u8,
+    },
+    /// Show a desktop notification
+    Desktop,
+    /// Ring the terminal bell
+    Bell,
+    /// Append the event to a file
+    Log { file: String },
+    /// Post the event to a webhook, at most once every `rate_limit_ms`
+    Webhook {
+        url: String,
+        /// JSON payload with placeholders, see `webhook::render_payload`
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        template: Option<String>,
+        #[serde(default)]
+        rate_limit_ms: u64,
+    },
+    /// Run a program with the event details in its environment and on stdin
+    Exec {
+        command: String,
+        #[serde(default)]
+        args: Vec<String>,
+        /// The program is killed if it runs for longer than this
+        #[serde(default = "default_exec_timeout_ms")]
+        timeout_ms: u64,
+        /// The action is skipped while this many copies of the program are running
+        #[serde(default = "default_exec_max_running")]
+        max_running: usize,
+    },
+    /// Hand the event to a notifier registered by a program built on Burrito
+    Custom { notifier: String },
+}
+
+impl AlertAction {
+    /// Sound action at full volume and the lowest priority
+    pub fn sound(file: &str) -> Self {
+        AlertAction::Sound { file: file.to_owned(), volume: default_volume(), priority: 0 }
+    }
+
+    /// Tone action at full volume and the lowest priority
+    pub fn tone(tone: Tone) -> Self {
+        AlertAction::Tone { tone, volume: default_volume(), priority: 0 }
+    }
+
+    /// Name of the notifier that runs this action
+    pub fn get_notifier_name(&self) -> &str {
+        match self {
+            AlertAction::Print => "terminal",
+            AlertAction::Sound { .. } | AlertAction::Tone { .. } => "audio",
+            AlertAction::Desktop => "desktop",
+            AlertAction::Bell => "bell",
+            AlertAction::Log { .. } => "file",
+            AlertAction::Webhook { .. } => "webhook",
+            AlertAction::Exec { .. } => "exec",
+            AlertAction::Custom { notifier } => notifier,
+        }
+    }
+}
+
+fn default_volume() -> u32 {
+    100
+}
+
+fn default_exec_timeout_ms() -> u64 {
+    10000
+}
+
+fn default_exec_max_running() -> usize {
+    1
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::{BTreeMap, BTreeSet};
+
+    use crate::burrito::{burrito_cfg::AudioAlert, log_watcher::{EventKind, EventType, LogEvent}, tones::Tone};
+
+    use super::{AlertAction, AlertCondition, AlertConfig, AlertRule, AlertStrategy};
+
+    fn event(event_type: EventType) -> LogEvent {
+        LogEvent::test(event_type)
+            .with_trigger("[ 2023.09.18 12:00:00 ] Reporter > UALX-3 neut")
+            .with_channel("east.imperium")
+            .with_reporter("Reporter")
+            .with_system("UALX-3")
+            .with_watched_system("1DQ1-A")
+    }
+
+    fn sound(file: &str) -> AlertAction {
+        AlertAction::sound(file)
+    }
+
+    #[test]
+    fn test_migrated_nearest_range() {
+        let audio_alerts = [
+            AudioAlert { trigger: EventType::RangeOfSystem(10), sound_file: "10.mp3".to_owned() },
+            AudioAlert { trigger: EventType::RangeOfSystem(5), sound_file: "5.mp3".to_owned() },
+            AudioAlert { trigger: EventType::FactionSpawn, sound_file: "faction.mp3".to_owned() },
+        ];
+        let uut = AlertConfig::from_audio_alerts(audio_alerts.iter());
+        let tags = BTreeMap::new();
+        assert_eq!(vec![AlertAction::Print, sound("5.mp3")], uut.get_actions(&event(EventType::RangeOfSystem(3)), &tags));
+        assert_eq!(vec![AlertAction::Print, sound("10.mp3")], uut.get_actions(&event(EventType::RangeOfSystem(7)), &tags));
+        assert!(uut.get_actions(&event(EventType::RangeOfSystem(11)), &tags).is_empty());
+        assert_eq!(vec![AlertAction::Print, sound("faction.mp3")], uut.get_actions(&event(EventType::FactionSpawn), &tags));
+        assert_eq!(vec![AlertAction::Print, AlertAction::tone(Tone::Alarm)], uut.get_actions(&event(EventType::SessionEnded), &tags));
+        assert_eq!(vec![AlertAction::Print, AlertAction::tone(Tone::Chirp)], uut.get_actions(&event(EventType::SystemClear(3)), &tags));
+        assert!(uut.get_actions(&event(EventType::SystemStatusRequest(8)), &tags).is_empty());
+    }
+
+    #[test]
+    fn test_conditions_and_priority() {
+        let mut tags = BTreeMap::new();
+        tags.insert("Alt A".to_owned(), BTreeSet::from(["ratting".to_owned()]));
+        let mut uut = AlertConfig {
+            strategy: AlertStrategy::AllMatches,
+            rules: vec![
+                AlertRule {
+                    name: "any".to_owned(),
+                    condition: AlertCondition { event_types: vec![EventKind::RangeOfSystem], ..Default::default() },
+                    actions: vec![sound("any.mp3")],
+                    priority: 0,
+                    stop_processing: false,
+                    escalation: None,
+                },
+                AlertRule {
+                    name: "ratting neut".to_owned(),
+                    condition: AlertCondition {
+                        character_tags: vec!["ratting".to_owned()],
+                        keywords: vec!["NEUT".to_owned()],
+                        channels: vec!["east.imperium".to_owned()],
+                        ..Default::default()
+                    },
+                    actions: vec![AlertAction::Print, sound("neut.mp3")],
+                    priority: 10,
+                    stop_processing: false,
+                    escalation: None,
+                },
+                AlertRule {
+                    name: "other system".to_owned(),
+                    condition: AlertCondition { systems: vec!["Jita".to_owned()], ..Default::default() },
+                    actions: vec![sound("jita.mp3")],
+                    priority: 20,
+                    stop_processing: false,
+                    escalation: None,
+                },
+            ],
+            coalesce_window_ms: 0,
+            needs_migration: false,
+        };
+        let tags_ref = &tags;
+        assert_eq!(
+            vec![AlertAction::Print, sound("neut.mp3"), sound("any.mp3")],
+            uut.get_actions(&event(EventType::RangeOfSystem(1)), tags_ref),
+        );
+        uut.rules[1].stop_processing = true;
+        assert_eq!(
+            vec![AlertAction::Print, sound("neut.mp3")],
+            uut.get_actions(&event(EventType::RangeOfSystem(1)), tags_ref),
+        );
+        uut.rules[1].stop_processing = false;
+        uut.strategy = AlertStrategy::FirstMatch;
+        assert_eq!(
+            vec![AlertAction::Print, sound("neut.mp3")],
+            uut.get_actions(&event(EventType::RangeOfSystem(1)), tags_ref),
+        );
+        assert_eq!(
+            vec![sound("any.mp3")],
+            uut.get_actions(&event(EventType::RangeOfSystem(1)), &BTreeMap::new()),
+        );
+    }
+}
@@ -0,0 +1,345 @@
+use std::{collections::{HashSet, VecDeque}, net::SocketAddr, sync::{mpsc::{self, Receiver, Sender}, Arc, Mutex}, thread};
+
+use serde_derive::{Deserialize, Serialize};
+use serde_json::json;
+use tiny_http::{Header, Method, Request, Response, Server, StatusCode};
+use tungstenite::{handshake::derive_accept_key, protocol::Role, Message, WebSocket};
+
+use super::{alert, burrito_cfg::ApiConfig, commands::Command, log_reader::LogReader, log_watcher::LogEvent, systems::SystemContext};
+
+/// A log file Burrito is reading, as reported by `GET /api/readers`
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct ReaderInfo {
+    pub character_name: String,
+    /// Channel of a chat log, `Local` etc., empty for game logs
+    pub channel_name: String,
+    pub log_file: String,
+    pub chatlog: bool,
+}
+
+impl ReaderInfo {
+    pub fn from_reader(reader: &LogReader) -> Self {
+        Self {
+            character_name: reader.get_character_name(),
+            channel_name: if reader.is_chatlog_reader() { reader.get_channel_name() } else { String::new() },
+            log_file: reader.get_log_file(),
+            chatlog: reader.is_chatlog_reader(),
+        }
+    }
+}
+
+/// What the endpoints report, kept up to date by the main loop
+#[derive(Default)]
+struct ApiState {
+    watched_systems: Vec<String>,
+    watched_characters: Vec<String>,
+    readers: Vec<ReaderInfo>,
+    recent_events: VecDeque<serde_json::Value>,
+    /// One sender for each connected WebSocket client
+    clients: Vec<Sender<String>>,
+}
+
+/// Body of `POST /api/watch` and `POST /api/unwatch`
+#[derive(Deserialize)]
+struct WatchRequest {
+    #[serde(default)]
+    system: Option<String>,
+    #[serde(default)]
+    character: Option<String>,
+}
+
+/// HTTP and WebSocket API on localhost for overlays and dashboards
+///
+/// Requests are served on a background thread. Events are streamed to
+/// WebSocket clients on `/api/stream` in the format of `--output json`.
+/// Watching and unwatching is queued as `Command`s for the main loop, which
+/// owns the `SystemContext`.
+///
+/// Browsers let any web page send requests to localhost, so requests from a page
+/// that is not on localhost or in `allowed_origins` are refused.
+pub struct ApiServer {
+    address: SocketAddr,
+    state: Arc<Mutex<ApiState>>,
+    commands: Receiver<Command>,
+    max_recent_events: usize,
+}
+
+impl ApiServer {
+    /// Starts serving on `127.0.0.1:port`, or on a free port if `port` is 0
+    ///
+    /// `system_names` are the systems that can be watched.
+    pub fn start(cfg: &ApiConfig, system_names: HashSet<String>) -> Result<Self, String> {
+        let port = cfg.port;
+        let allowed_origins = cfg.allowed_origins.clone();
+        let server = Server::http(("127.0.0.1", port)).map_err(|e| format!("Unable to start the API on port {port}: {e}"))?;
+        let address = server.server_addr().to_ip().ok_or("The API is not listening on an IP address")?;
+        let state = Arc::new(Mutex::new(ApiState::default()));
+        let (sender, commands) = mpsc::channel();
+        let server_state = state.clone();
+        thread::spawn(move || {
+            for request in server.incoming_requests() {
+                if let Err(e) = check_origin(&request, &allowed_origins) {
+                    _ = request.respond(error_response(403, &e));
+                    continue;
+                }
+                handle_request(request, &server_state, &sender, &system_names);
+            }
+        });
+        Ok(Self {
+            address,
+            state,
+            commands,
+            max_recent_events: cfg.recent_events,
+        })
+    }
+
+    pub fn get_address(&self) -> SocketAddr {
+        self.address
+    }
+
+    /// Updates the watched systems, characters and readers reported by the API
+    pub fn update(&self, ctx: &SystemContext, readers: &[LogReader]) {
+        let mut state = self.state.lock().unwrap();
+        state.watched_systems = ctx.get_current_systems().iter().cloned().collect();
+        state.watched_systems.sort();
+        state.watched_characters = ctx.get_current_characters().iter().cloned().collect();
+        state.watched_characters.sort();
+        state.readers = readers.iter().map(ReaderInfo::from_reader).collect();
+    }
+
+    /// Streams an event to WebSocket clients and keeps it for `GET /api/events`
+    pub fn publish(&self, event: &LogEvent, alert: bool) {
+        let json = alert::get_alert_json(event, alert);
+        let text = json.to_string();
+        let mut state = self.state.lock().unwrap();
+        // Clients that disconnected have dropped their receiver
+        state.clients.retain(|client| client.send(text.clone()).is_ok());
+        state.recent_events.push_back(json);
+        while state.recent_events.len() > self.max_recent_events {
+            state.recent_events.pop_front();
+        }
+    }
+
+    /// Commands from requests since the last call
+    pub fn get_commands(&self) -> Vec<Command> {
+        self.commands.try_iter().collect()
+    }
+}
+
+fn handle_request(mut request: Request, state: &Arc<Mutex<ApiState>>, commands: &Sender<Command>, system_names: &HashSet<String>) {
+    let url = request.url().to_owned();
+    let (path, query) = url.split_once('?').unwrap_or((&url, ""));
+    let response = match (request.method(), path) {
+        (Method::Get, "/api/stream") if is_websocket(&request) => {
+            accept_websocket(request, state);
+            return;
+        },
+        (Method::Get, "/api/watched") => {
+            let state = state.lock().unwrap();
+            json_response(200, json!({"systems": state.watched_systems, "characters": state.watched_characters}))
+        },
+        (Method::Get, "/api/readers") => json_response(200, json!(state.lock().unwrap().readers)),
+        (Method::Get, "/api/events") => {
+            let limit = query.split('&')
+                .find_map(|param| param.strip_prefix("limit="))
+                .and_then(|limit| limit.parse::<usize>().ok());
+            let state = state.lock().unwrap();
+            let skip = limit.map(|limit| state.recent_events.len().saturating_sub(limit)).unwrap_or(0);
+            json_response(200, json!(state.recent_events.iter().skip(skip).collect::<Vec<_>>()))
+        },
+        (Method::Post, "/api/watch") | (Method::Post, "/api/unwatch") if !is_json(&request) => {
+            error_response(415, "Expected Content-Type: application/json")
+        },
+        (Method::Post, "/api/watch") | (Method::Post, "/api/unwatch") => {
+            let watch = path == "/api/watch";
+            match read_watch_command(&mut request, watch, system_names) {
+                Ok(command) => {
+                    _ = commands.send(command);
+                    json_response(202, json!({"status": "accepted"}))
+                },
+                Err(e) => error_response(400, &e),
+            }
+        },
+        _ => error_response(404, &format!("No endpoint {} {path}", request.method())),
+    };
+    _ = request.respond(response);
+}
+
+fn read_watch_command(request: &mut Request, watch: bool, system_names: &HashSet<String>) -> Result<Command, String> {
+    let body: WatchRequest = serde_json::from_reader(request.as_reader())
+        .map_err(|e| format!("Expected {{\"system\": ...}} or {{\"character\": ...}}: {e}"))?;
+    match (body.system, body.character) {
+        (Some(system), None) if !system_names.contains(&system) => Err(format!("Unknown system name: {system}")),
+        (Some(system), None) if watch => Ok(Command::WatchSystem(system)),
+        (Some(system), None) => Ok(Command::UnwatchSystem(system)),
+        (None, Some(character)) if watch => Ok(Command::WatchCharacter(character)),
+        (None, Some(character)) => Ok(Command::UnwatchCharacter(character)),
+        _ => Err("Expected either \"system\" or \"character\"".to_owned()),
+    }
+}
+
+fn get_header<'a>(request: &'a Request, name: &'static str) -> Option<&'a str> {
+    request.headers().iter().find(|h| h.field.equiv(name)).map(|h| h.value.as_str())
+}
+
+/// Refuses requests sent by web pages that are neither on localhost nor allowed
+///
+/// Requests without an `Origin`, e.g. from scripts, are not sent by web pages.
+fn check_origin(request: &Request, allowed_origins: &[String]) -> Result<(), String> {
+    let origin = match get_header(request, "Origin") {
+        Some(origin) => origin,
+        None => return Ok(()),
+    };
+    let host = origin.split_once("://").map(|(_, rest)| rest).unwrap_or("");
+    let host = match host.strip_prefix('[') {
+        Some(ipv6) => ipv6.split(']').next().unwrap_or(""),
+        None => host.split(':').next().unwrap_or(""),
+    };
+    let local = ["localhost", "127.0.0.1", "::1"].contains(&host);
+    if local || allowed_origins.iter().any(|allowed| allowed.trim_end_matches('/').eq_ignore_ascii_case(origin)) {
+        Ok(())
+    }
+    else {
+        Err(format!("Origin {origin} is not allowed, add it to api_config -> allowed_origins"))
+    }
+}
+
+fn is_json(request: &Request) -> bool {
+    get_header(request, "Content-Type")
+        .map(|content_type| content_type.split(';').next().unwrap_or("").trim().eq_ignore_ascii_case("application/json"))
+        .unwrap_or(false)
+}
+
+fn is_websocket(request: &Request) -> bool {
+    request.headers().iter().any(|h| h.field.equiv("Upgrade") && h.value.as_str().eq_ignore_ascii_case("websocket"))
+}
+
+fn accept_websocket(request: Request, state: &Arc<Mutex<ApiState>>) {
+    let key = match get_header(&request, "Sec-WebSocket-Key") {
+        Some(key) => key.to_owned(),
+        None => {
+            _ = request.respond(error_response(400, "Missing Sec-WebSocket-Key"));
+            return;
+        },
+    };
+    let response = Response::new_empty(StatusCode(101))
+        .with_header(header("Upgrade", "websocket"))
+        .with_header(header("Connection", "Upgrade"))
+        .with_header(header("Sec-WebSocket-Accept", &derive_accept_key(key.as_bytes())));
+    let stream = request.upgrade("websocket", response);
+    let mut socket = WebSocket::from_raw_socket(stream, Role::Server, None);
+    let (sender, receiver) = mpsc::channel::<String>();
+    state.lock().unwrap().clients.push(sender);
+    thread::spawn(move || {
+        for text in receiver {
+            if socket.send(Message::Text(text)).is_err() {
+                return;
+            }
+        }
+        _ = socket.close(None);
+    });
+}
+
+fn header(name: &str, value: &str) -> Header {
+    Header::from_bytes(name.as_bytes(), value.as_bytes()).unwrap()
+}
+
+fn json_response(status: u16, body: serde_json::Value) -> Response<std::io::Cursor<Vec<u8>>> {
+    Response::from_string(body.to_string())
+        .with_status_code(status)
+        .with_header(header("Content-Type", "application/json"))
+}
+
+fn error_response(status: u16, message: &str) -> Response<std::io::Cursor<Vec<u8>>> {
+    json_response(status, json!({"error": message}))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashSet, time::{Duration, Instant}};
+
+    use tungstenite::{client::IntoClientRequest, Message};
+
+    use crate::burrito::{burrito_cfg::ApiConfig, commands::Command, log_watcher::{EventType, LogEvent}, systems::SystemContext};
+
+    use super::ApiServer;
+
+    fn get_json(url: &str) -> serde_json::Value {
+        serde_json::from_str(&ureq::get(url).call().unwrap().into_string().unwrap()).unwrap()
+    }
+
+    /// Status code of a request, which ureq reports as an error for 4xx and 5xx
+    fn get_status(result: Result<ureq::Response, ureq::Error>) -> u16 {
+        match result {
+            Ok(response) => response.status(),
+            Err(ureq::Error::Status(status, _)) => status,
+            Err(e) => panic!("Request failed: {e}"),
+        }
+    }
+
+    fn post_json(url: &str, body: serde_json::Value) -> u16 {
+        get_status(ureq::post(url).set("Content-Type", "application/json").send_string(&body.to_string()))
+    }
+
+    #[test]
+    fn test_api() {
+        let cfg = ApiConfig { enabled: true, port: 0, recent_events: 2, allowed_origins: vec![] };
+        let uut = ApiServer::start(&cfg, HashSet::from(["Jita".to_owned()])).unwrap();
+        let url = format!("http://{}/api", uut.get_address());
+        uut.update(&SystemContext::default(), &[]);
+        assert_eq!(serde_json::json!({"systems": [], "characters": []}), get_json(&format!("{url}/watched")));
+
+        let (mut socket, _) = tungstenite::connect(format!("ws://{}/api/stream", uut.get_address())).unwrap();
+        let start = Instant::now();
+        while uut.state.lock().unwrap().clients.is_empty() {
+            assert!(start.elapsed() < Duration::from_secs(5), "WebSocket client was not registered");
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        for jumps in 1..=3 {
+            let event = LogEvent::test(EventType::RangeOfSystem(jumps)).with_trigger("Reporter > Jita +1");
+            uut.publish(&event, jumps == 1);
+        }
+        let message = match socket.read().unwrap() {
+            Message::Text(text) => serde_json::from_str::<serde_json::Value>(&text).unwrap(),
+            message => panic!("Unexpected message {message:?}"),
+        };
+        assert_eq!(1, message["distance"]);
+        assert_eq!(true, message["alert"]);
+
+        // Only the most recent events are kept
+        let events = get_json(&format!("{url}/events"));
+        assert_eq!(serde_json::json!([2, 3]), serde_json::json!([events[0]["distance"], events[1]["distance"]]));
+        assert_eq!(1, get_json(&format!("{url}/events?limit=1")).as_array().unwrap().len());
+
+        assert_eq!(202, post_json(&format!("{url}/watch"), serde_json::json!({"system": "Jita"})));
+        assert_eq!(202, post_json(&format!("{url}/unwatch"), serde_json::json!({"character": "Bad Guy"})));
+        assert_eq!(vec![Command::WatchSystem("Jita".to_owned()), Command::UnwatchCharacter("Bad Guy".to_owned())], uut.get_commands());
+
+        assert_eq!(400, post_json(&format!("{url}/watch"), serde_json::json!({"system": "Nowhere"})));
+        assert_eq!(400, post_json(&format!("{url}/watch"), serde_json::json!({"system": "Jita", "character": "Bad Guy"})));
+        assert_eq!(404, get_status(ureq::get(&format!("{url}/nothing")).call()));
+    }
+
+    #[test]
+    fn test_api_refuses_other_web_pages() {
+        let cfg = ApiConfig { enabled: true, port: 0, recent_events: 2, allowed_origins: vec!["https://overlay.example.com".to_owned()] };
+        let uut = ApiServer::start(&cfg, HashSet::from(["Jita".to_owned()])).unwrap();
+        let url = format!("http://{}/api", uut.get_address());
+        let get_from = |origin: &str| get_status(ureq::get(&format!("{url}/watched")).set("Origin", origin).call());
+        assert_eq!(200, get_from("http://localhost:3000"));
+        assert_eq!(200, get_from("http://127.0.0.1"));
+        assert_eq!(200, get_from("https://overlay.example.com"));
+        assert_eq!(403, get_from("https://evil.example.com"));
+        assert_eq!(403, get_from("http://localhost.evil.example.com"));
+
+        let mut request = format!("ws://{}/api/stream", uut.get_address()).into_client_request().unwrap();
+        request.headers_mut().insert("Origin", "https://evil.example.com".parse().unwrap());
+        assert!(tungstenite::connect(request).is_err());
+
+        // A page can post plain text without asking first, but not JSON
+        let body = serde_json::json!({"system": "Jita"}).to_string();
+        assert_eq!(415, get_status(ureq::post(&format!("{url}/watch")).set("Content-Type", "text/plain").send_string(&body)));
+        assert_eq!(415, get_status(ureq::post(&format!("{url}/watch")).send_string(&body)));
+        assert!(uut.get_commands().is_empty());
+    }
+}
@@ -0,0 +1,769 @@
+use std::{cmp::Ordering, collections::{BinaryHeap, HashMap}, fs::File, io::BufReader, path::Path, sync::{mpsc::{self, Receiver, RecvTimeoutError, Sender}, Arc, Mutex}, thread::{self, JoinHandle}, time::{Duration, Instant}};
+
+use rodio::{buffer::SamplesBuffer, cpal::{self, traits::HostTrait}, Decoder, DeviceTrait, OutputStream, OutputStreamHandle, Sink, Source};
+
+use super::{burrito_cfg::{AudioAlertConfig, AudioBackendKind}, diagnostics, tones::Tone};
+
+const POLL_INTERVAL_MS: u64 = 20;
+const DEVICE_CHECK_INTERVAL_MS: u64 = 3000;
+
+/// Something to play
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub enum Sound {
+    File(String),
+    Tone(Tone),
+}
+
+impl std::fmt::Display for Sound {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Sound::File(file) => write!(f, "{file}"),
+            Sound::Tone(tone) => write!(f, "{tone} tone"),
+        }
+    }
+}
+
+/// A sound that an alert wants played
+#[derive(Clone, Debug, PartialEq)]
+pub struct SoundRequest {
+    pub sound: Sound,
+    /// Volume in percent of the file's own volume
+    pub volume: u32,
+    /// Sounds with a higher priority play first and interrupt lower priority sounds
+    pub priority: u8,
+}
+
+#[derive(Debug)]
+struct QueuedSound {
+    request: SoundRequest,
+    seq: u64,
+}
+
+impl Ord for QueuedSound {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // Highest priority first, then first come first served
+        self.request.priority.cmp(&other.request.priority)
+            .then_with(|| other.seq.cmp(&self.seq))
+    }
+}
+
+impl PartialOrd for QueuedSound {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for QueuedSound {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for QueuedSound {}
+
+/// Sounds waiting to be played
+///
+/// A sound that is requested again within `dedup_window_ms` of being queued is
+/// dropped, so a burst of reports plays it only once.
+#[derive(Debug, Default)]
+pub struct SoundQueue {
+    queue: BinaryHeap<QueuedSound>,
+    last_queued: HashMap<Sound, Instant>,
+    dedup_window_ms: u64,
+    next_seq: u64,
+}
+
+impl SoundQueue {
+    pub fn new(dedup_window_ms: u64) -> Self {
+        Self {
+            dedup_window_ms,
+            ..Default::default()
+        }
+    }
+
+    /// Queues a sound, returns false if it was dropped as a duplicate
+    pub fn push(&mut self, request: SoundRequest, now: Instant) -> bool {
+        if let Some(last_queued) = self.last_queued.get(&request.sound) {
+            if now.duration_since(*last_queued) < Duration::from_millis(self.dedup_window_ms) {
+                return false;
+            }
+        }
+        let window = Duration::from_millis(self.dedup_window_ms);
+        self.last_queued.retain(|_, queued| now.duration_since(*queued) < window);
+        self.last_queued.insert(request.sound.clone(), now);
+        self.queue.push(QueuedSound { request, seq: self.next_seq });
+        self.next_seq += 1;
+        true
+    }
+
+    /// Takes the sound that should play next
+    ///
+    /// `playing` is the priority of the sound that is currently playing, if any.
+    /// While a sound plays, only a sound with a higher priority is returned, and it
+    /// should interrupt the playing sound.
+    pub fn pop_next(&mut self, playing: Option<u8>) -> Option<SoundRequest> {
+        let next = self.queue.peek()?;
+        match playing {
+            Some(priority) if next.request.priority <= priority => None,
+            _ => self.queue.pop().map(|queued| queued.request),
+        }
+    }
+
+    /// Queues a sound that was cut off, ahead of sounds with the same priority
+    ///
+    /// It is never dropped as a duplicate, since it did not finish playing.
+    pub fn push_again(&mut self, request: SoundRequest) {
+        self.queue.push(QueuedSound { request, seq: 0 });
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.queue.is_empty()
+    }
+}
+
+/// A sound file decoded into memory
+#[derive(Debug)]
+pub struct DecodedSound {
+    channels: u16,
+    sample_rate: u32,
+    samples: Vec<i16>,
+}
+
+impl DecodedSound {
+    fn get_source(&self) -> SamplesBuffer<i16> {
+        SamplesBuffer::new(self.channels, self.sample_rate, self.samples.clone())
+    }
+}
+
+/// Decodes an mp3, ogg, wav or flac file
+pub fn decode_file(file: &str) -> Result<DecodedSound, String> {
+    if !Path::new(file).is_file() {
+        return Err(format!("Sound file {file} does not exist"));
+    }
+    let reader = File::open(file).map_err(|e| format!("Unable to open sound file {file}: {e}"))?;
+    let decoder = Decoder::new(BufReader::new(reader))
+        .map_err(|e| format!("Unable to decode sound file {file}, supported formats are mp3, ogg, wav and flac: {e}"))?;
+    let channels = decoder.channels();
+    let sample_rate = decoder.sample_rate();
+    Ok(DecodedSound { channels, sample_rate, samples: decoder.collect() })
+}
+
+/// Sound files decoded ahead of time, so alerts do not wait on the disk and broken
+/// files are found when Burrito starts rather than when an alert fires
+#[derive(Clone, Debug, Default)]
+pub struct SoundCache {
+    sounds: HashMap<String, Arc<DecodedSound>>,
+}
+
+impl SoundCache {
+    /// Decodes every file, returning the cache and an error for each file that
+    /// could not be decoded
+    pub fn load<'a>(files: impl IntoIterator<Item = &'a String>) -> (Self, Vec<String>) {
+        let mut cache = Self::default();
+        let mut errors = vec![];
+        for file in files {
+            match decode_file(file) {
+                Ok(sound) => _ = cache.sounds.insert(file.to_owned(), Arc::new(sound)),
+                Err(e) => errors.push(e),
+            }
+        }
+        (cache, errors)
+    }
+
+    pub fn contains(&self, file: &str) -> bool {
+        self.sounds.contains_key(file)
+    }
+
+    /// Decoded sound, decoding files that are not cached from disk
+    fn get(&self, sound: &Sound) -> Result<Arc<DecodedSound>, String> {
+        match sound {
+            Sound::File(file) => match self.sounds.get(file) {
+                Some(sound) => Ok(sound.clone()),
+                None => decode_file(file).map(Arc::new),
+            },
+            Sound::Tone(tone) => Ok(Arc::new(DecodedSound {
+                channels: 1,
+                sample_rate: Tone::get_sample_rate(),
+                samples: tone.synthesize(),
+            })),
+        }
+    }
+}
+
+/// What became of the most recent sound of a backend
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum PlayState {
+    Playing,
+    Finished,
+    /// Cut off before its end, e.g. because its device was unplugged
+    Interrupted,
+}
+
+/// Something that can play decoded sounds
+///
+/// The engine plays one sound at a time, so a backend only has to keep track of
+/// its most recent sound.
+pub trait AudioBackend {
+    fn play(&mut self, request: &SoundRequest, sound: &DecodedSound) -> Result<(), String>;
+    fn get_state(&mut self) -> PlayState;
+    fn stop(&mut self);
+}
+
+/// Output devices that existed when an output was opened, to notice when one is
+/// plugged in or removed
+struct DeviceWatch {
+    device_names: Vec<String>,
+    last_check: Instant,
+    interval: Duration,
+}
+
+impl DeviceWatch {
+    fn new(device_names: Vec<String>, interval: Duration) -> Self {
+        Self { device_names, last_check: Instant::now(), interval }
+    }
+
+    /// Whether `list_devices` returns other devices than when the output was opened
+    ///
+    /// Devices are only listed every `interval`, unless `now` is set.
+    fn changed(&mut self, now: bool, list_devices: impl FnOnce() -> Result<Vec<String>, String>) -> bool {
+        if !now && self.last_check.elapsed() < self.interval {
+            return false;
+        }
+        self.last_check = Instant::now();
+        match list_devices() {
+            Ok(names) => names != self.device_names,
+            Err(_) => true,
+        }
+    }
+}
+
+/// Plays sounds on an audio output device
+pub struct DeviceBackend {
+    /// Name of the wanted device, or the default device if unset
+    device: Option<String>,
+    output: Option<Output>,
+    sink: Option<Sink>,
+}
+
+impl DeviceBackend {
+    pub fn new(device: Option<String>) -> Self {
+        Self { device, output: None, sink: None }
+    }
+}
+
+impl AudioBackend for DeviceBackend {
+    fn play(&mut self, request: &SoundRequest, sound: &DecodedSound) -> Result<(), String> {
+        // A sound on a stream whose device is gone would never be heard
+        if self.output.as_mut().map(|output| output.watch.changed(true, get_device_names)).unwrap_or(false) {
+            diagnostics::report("Audio devices changed, reconnecting");
+            self.output = None;
+        }
+        if self.output.is_none() {
+            self.output = Some(open_output(self.device.as_deref())?);
+        }
+        let sink = match Sink::try_new(&self.output.as_ref().unwrap().handle) {
+            Ok(sink) => sink,
+            Err(e) => {
+                // The device may be gone, open the stream again next time
+                self.output = None;
+                return Err(e.to_string());
+            },
+        };
+        sink.set_volume(request.volume as f32 / 100.0);
+        sink.append(sound.get_source());
+        self.sink = Some(sink);
+        Ok(())
+    }
+
+    fn get_state(&mut self) -> PlayState {
+        if self.sink.as_ref().map(|sink| sink.empty()).unwrap_or(true) {
+            return PlayState::Finished;
+        }
+        if self.output.as_mut().map(|output| output.watch.changed(false, get_device_names)).unwrap_or(false) {
+            // A sound on a device that is gone would never finish
+            diagnostics::report("Audio devices changed, reconnecting");
+            self.sink = None;
+            self.output = None;
+            return PlayState::Interrupted;
+        }
+        PlayState::Playing
+    }
+
+    fn stop(&mut self) {
+        if let Some(sink) = self.sink.take() {
+            sink.stop();
+        }
+    }
+}
+
+/// Plays nothing, for machines without an audio device
+pub struct NullBackend;
+
+impl AudioBackend for NullBackend {
+    fn play(&mut self, _request: &SoundRequest, _sound: &DecodedSound) -> Result<(), String> {
+        Ok(())
+    }
+
+    fn get_state(&mut self) -> PlayState {
+        PlayState::Finished
+    }
+
+    fn stop(&mut self) {}
+}
+
+/// Plays nothing but remembers every sound it was asked to play
+pub struct RecordingBackend {
+    played: Arc<Mutex<Vec<SoundRequest>>>,
+    /// Simulated output devices, which tests change to plug in or remove one
+    devices: Option<Arc<Mutex<Vec<String>>>>,
+    /// How long each sound plays
+    duration: Duration,
+    watch: Option<DeviceWatch>,
+    started: Option<Instant>,
+}
+
+impl RecordingBackend {
+    /// Backend that adds the sounds it plays to `played`
+    pub fn new(played: Arc<Mutex<Vec<SoundRequest>>>) -> Self {
+        Self { played, devices: None, duration: Duration::ZERO, watch: None, started: None }
+    }
+
+    /// Backend on the simulated `devices` whose sounds play for `duration`
+    ///
+    /// Like on a real device, a sound played after the devices changed since the
+    /// output was opened is lost and not added to `played`.
+    pub fn with_devices(played: Arc<Mutex<Vec<SoundRequest>>>, devices: Arc<Mutex<Vec<String>>>, duration: Duration) -> Self {
+        Self { devices: Some(devices), duration, ..Self::new(played) }
+    }
+
+    fn list_devices(&self) -> Result<Vec<String>, String> {
+        match &self.devices {
+            Some(devices) => Ok(devices.lock().map_err(|e| e.to_string())?.clone()),
+            None => Ok(vec![]),
+        }
+    }
+}
+
+impl AudioBackend for RecordingBackend {
+    fn play(&mut self, request: &SoundRequest, _sound: &DecodedSound) -> Result<(), String> {
+        let devices = self.list_devices()?;
+        if self.watch.as_mut().map(|watch| watch.changed(true, || Ok(devices.clone()))).unwrap_or(false) {
+            self.watch = None;
+        }
+        let watch = self.watch.get_or_insert_with(|| DeviceWatch::new(devices.clone(), Duration::from_millis(POLL_INTERVAL_MS)));
+        if watch.device_names == devices {
+            self.played.lock().map_err(|e| e.to_string())?.push(request.clone());
+        }
+        self.started = Some(Instant::now());
+        Ok(())
+    }
+
+    fn get_state(&mut self) -> PlayState {
+        if self.started.map(|started| started.elapsed() >= self.duration).unwrap_or(true) {
+            return PlayState::Finished;
+        }
+        let devices = self.list_devices();
+        if self.watch.as_mut().map(|watch| watch.changed(false, || devices)).unwrap_or(false) {
+            self.watch = None;
+            self.started = None;
+            return PlayState::Interrupted;
+        }
+        PlayState::Playing
+    }
+
+    fn stop(&mut self) {
+        self.started = None;
+    }
+}
+
+/// Plays alert sounds one at a time through an `AudioBackend`
+///
+/// Sounds are played from a background thread in priority order. The device
+/// backend keeps a single long-lived output stream. Problems such as missing
+/// files or a missing audio device are reported instead of stopping Burrito.
+pub struct AudioEngine {
+    sender: Sender<SoundRequest>,
+    thread: JoinHandle<()>,
+    sounds: SoundCache,
+}
+
+impl AudioEngine {
+    /// Engine with the backend selected by `audio_backend`
+    pub fn new(cfg: &AudioAlertConfig, sounds: SoundCache) -> Self {
+        let use_device = match cfg.audio_backend {
+            AudioBackendKind::Device => true,
+            AudioBackendKind::Null => false,
+            AudioBackendKind::Auto => match check_default_output() {
+                Ok(()) => true,
+                Err(e) => {
+                    diagnostics::report(format!("No usable audio device found ({e}), sounds will not be played"));
+                    false
+                },
+            },
+        };
+        let device = cfg.audio_device.to_owned();
+        Self::with_backend(cfg, sounds, move || -> Box<dyn AudioBackend> {
+            if use_device {
+                Box::new(DeviceBackend::new(device))
+            }
+            else {
+                Box::new(NullBackend)
+            }
+        })
+    }
+
+    /// Engine that plays sounds through the backend made by `make_backend`
+    ///
+    /// The backend is made on the engine's thread, so it does not have to be `Send`.
+    pub fn with_backend<F>(cfg: &AudioAlertConfig, sounds: SoundCache, make_backend: F) -> Self
+    where
+        F: FnOnce() -> Box<dyn AudioBackend> + Send + 'static,
+    {
+        let (sender, receiver) = mpsc::channel();
+        let queue = SoundQueue::new(cfg.dedup_window_ms);
+        let engine_sounds = sounds.clone();
+        let thread = thread::spawn(move || run_engine(receiver, queue, engine_sounds, make_backend()));
+        Self { sender, thread, sounds }
+    }
+
+    /// Whether a sound file was decoded when the engine was created
+    pub fn has_file(&self, file: &str) -> bool {
+        self.sounds.contains(file)
+    }
+
+    pub fn play(&self, request: SoundRequest) -> Result<(), String> {
+        self.sender.send(request).map_err(|_| "Audio engine is not running".to_owned())
+    }
+
+    /// Waits for every queued sound to finish playing
+    pub fn finish(self) {
+        drop(self.sender);
+        _ = self.thread.join();
+    }
+}
+
+/// An open output stream and the devices that existed when it was opened
+struct Output {
+    _stream: OutputStream,
+    handle: OutputStreamHandle,
+    watch: DeviceWatch,
+}
+
+/// Names of the output devices of the default audio host
+pub fn get_device_names() -> Result<Vec<String>, String> {
+    let devices = cpal::default_host().output_devices().map_err(|e| e.to_string())?;
+    Ok(devices.map(|d| d.name().unwrap_or_default()).collect())
+}
+
+/// Name of the default output device, if there is one
+pub fn get_default_device_name() -> Option<String> {
+    cpal::default_host().default_output_device().and_then(|d| d.name().ok())
+}
+
+/// Checks that there is a default output device and that it can be opened
+///
+/// Listing devices is not enough, since ALSA lists pseudo devices like `null`
+/// on machines without sound hardware.
+fn check_default_output() -> Result<(), String> {
+    cpal::default_host().default_output_device().ok_or("no default output device")?;
+    OutputStream::try_default().map(|_| ()).map_err(|e| e.to_string())
+}
+
+/// Index of the device that best matches `wanted`
+///
+/// An exact name wins over a name that merely contains `wanted`. Names are not
+/// case-sensitive.
+pub fn select_device(device_names: &[String], wanted: &str) -> Option<usize> {
+    let wanted = wanted.to_lowercase();
+    device_names.iter().position(|name| name.to_lowercase() == wanted)
+        .or_else(|| device_names.iter().position(|name| name.to_lowercase().contains(&wanted)))
+}
+
+/// Opens the configured device, falling back to the default device
+fn open_output(wanted: Option<&str>) -> Result<Output, String> {
+    let devices: Vec<cpal::Device> = cpal::default_host().output_devices().map_err(|e| e.to_string())?.collect();
+    let device_names: Vec<String> = devices.iter().map(|d| d.name().unwrap_or_default()).collect();
+    let mut stream = None;
+    if let Some(wanted) = wanted {
+        match select_device(&device_names, wanted) {
+            Some(i) => match OutputStream::try_from_device(&devices[i]) {
+                Ok(s) => stream = Some(s),
+                Err(e) => diagnostics::report(format!("Unable to use audio device {}, using the default device: {}", device_names[i], e)),
+            },
+            None => diagnostics::report(format!("Audio device {wanted} not found, using the default device")),
+        }
+    }
+    let (stream, handle) = match stream {
+        Some(stream) => stream,
+        None => OutputStream::try_default().map_err(|e| e.to_string())?,
+    };
+    let watch = DeviceWatch::new(device_names, Duration::from_millis(DEVICE_CHECK_INTERVAL_MS));
+    Ok(Output { _stream: stream, handle, watch })
+}
+
+fn run_engine(receiver: Receiver<SoundRequest>, mut queue: SoundQueue, sounds: SoundCache, mut backend: Box<dyn AudioBackend>) {
+    let mut playing: Option<SoundRequest> = None;
+    let mut connected = true;
+    loop {
+        if connected {
+            let received = if playing.is_none() && queue.is_empty() {
+                receiver.recv().map_err(|_| RecvTimeoutError::Disconnected)
+            }
+            else {
+                receiver.recv_timeout(Duration::from_millis(POLL_INTERVAL_MS))
+            };
+            match received {
+                Ok(request) => _ = queue.push(request, Instant::now()),
+                Err(RecvTimeoutError::Timeout) => {},
+                Err(RecvTimeoutError::Disconnected) => connected = false,
+            }
+            while let Ok(request) = receiver.try_recv() {
+                queue.push(request, Instant::now());
+            }
+        }
+        else {
+            thread::sleep(Duration::from_millis(POLL_INTERVAL_MS));
+        }
+        if playing.is_some() {
+            match backend.get_state() {
+                PlayState::Playing => {},
+                PlayState::Finished => playing = None,
+                // Played again from the start, on the device that is there now
+                PlayState::Interrupted => queue.push_again(playing.take().unwrap()),
+            }
+        }
+        if !connected && playing.is_none() && queue.is_empty() {
+            return;
+        }
+        if let Some(request) = queue.pop_next(playing.as_ref().map(|playing| playing.priority)) {
+            if playing.take().is_some() {
+                backend.stop();
+            }
+            let result = sounds.get(&request.sound).and_then(|sound| backend.play(&request, &sound));
+            match result {
+                Ok(_) => playing = Some(request),
+                Err(e) => diagnostics::report(format!("Unable to play {}: {}", request.sound, e)),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs::OpenOptions, io::Write, path::Path, sync::{Arc, Mutex}, thread, time::{Duration, Instant}};
+
+    use crate::burrito::{
+        alert_rules::{AlertAction, AlertCondition, AlertConfig, AlertRule},
+        burrito_cfg::BurritoCfg,
+        burrito_data::BurritoData,
+        log_watcher::{EventKind, EventType, LogEvent, LogWatcher},
+        notifier::{AudioNotifier, Notifier, Notifiers},
+        systems::{SystemContext, SystemMap},
+        tones::Tone,
+        utils::TestDir,
+    };
+
+    use super::{select_device, AudioEngine, RecordingBackend, Sound, SoundCache, SoundQueue, SoundRequest};
+
+    fn sound(file: &str, priority: u8) -> SoundRequest {
+        SoundRequest { sound: Sound::File(file.to_owned()), volume: 100, priority }
+    }
+
+    /// Writes 100 samples of 16 bit mono PCM at 8000 Hz
+    fn write_wav(path: &Path) -> String {
+        let mut wav = vec![];
+        wav.extend_from_slice(b"RIFF");
+        wav.extend_from_slice(&(36u32 + 200).to_le_bytes());
+        wav.extend_from_slice(b"WAVEfmt ");
+        wav.extend_from_slice(&16u32.to_le_bytes());
+        wav.extend_from_slice(&1u16.to_le_bytes());
+        wav.extend_from_slice(&1u16.to_le_bytes());
+        wav.extend_from_slice(&8000u32.to_le_bytes());
+        wav.extend_from_slice(&16000u32.to_le_bytes());
+        wav.extend_from_slice(&2u16.to_le_bytes());
+        wav.extend_from_slice(&16u16.to_le_bytes());
+        wav.extend_from_slice(b"data");
+        wav.extend_from_slice(&200u32.to_le_bytes());
+        wav.extend(std::iter::repeat(0u8).take(200));
+        std::fs::write(path, wav).unwrap();
+        path.to_string_lossy().into_owned()
+    }
+
+    #[test]
+    fn test_sound_queue_priority() {
+        let now = Instant::now();
+        let mut uut = SoundQueue::new(0);
+        uut.push(sound("low_1.mp3", 0), now);
+        uut.push(sound("low_2.mp3", 0), now);
+        uut.push(sound("high.mp3", 5), now);
+        assert_eq!(Some(sound("high.mp3", 5)), uut.pop_next(None));
+        // Same priority waits for the playing sound to finish
+        assert_eq!(None, uut.pop_next(Some(0)));
+        assert_eq!(Some(sound("low_1.mp3", 0)), uut.pop_next(None));
+        uut.push(sound("urgent.mp3", 9), now);
+        assert_eq!(Some(sound("urgent.mp3", 9)), uut.pop_next(Some(0)));
+        assert_eq!(Some(sound("low_2.mp3", 0)), uut.pop_next(None));
+        assert!(uut.is_empty());
+    }
+
+    #[test]
+    fn test_sound_queue_dedup() {
+        let now = Instant::now();
+        let mut uut = SoundQueue::new(2000);
+        assert!(uut.push(sound("neut.mp3", 0), now));
+        assert!(!uut.push(sound("neut.mp3", 0), now + Duration::from_millis(1000)));
+        assert!(uut.push(sound("clear.mp3", 0), now + Duration::from_millis(1000)));
+        assert!(uut.push(sound("neut.mp3", 0), now + Duration::from_millis(2500)));
+    }
+
+    #[test]
+    fn test_select_device() {
+        let devices = vec![
+            "default".to_owned(),
+            "HyperX Cloud II Wireless, USB Audio".to_owned(),
+            "HDA Intel PCH, ALC892 Analog".to_owned(),
+            "pipewire".to_owned(),
+        ];
+        assert_eq!(Some(1), select_device(&devices, "hyperx"));
+        assert_eq!(Some(3), select_device(&devices, "PipeWire"));
+        assert_eq!(Some(0), select_device(&devices, "default"));
+        assert_eq!(None, select_device(&devices, "Bluetooth"));
+    }
+
+    #[test]
+    fn test_sound_cache() {
+        let dir = TestDir::new("sound_cache");
+        let wav_file = write_wav(&dir.join("beep.wav"));
+        let text_file = dir.join("notes.mp3").to_string_lossy().into_owned();
+        std::fs::write(&text_file, "not a sound").unwrap();
+        let missing_file = dir.join("missing.mp3").to_string_lossy().into_owned();
+
+        let files = vec![wav_file.to_owned(), text_file.to_owned(), missing_file.to_owned()];
+        let (uut, errors) = SoundCache::load(&files);
+        assert!(uut.contains(&wav_file));
+        assert_eq!(100, uut.get(&Sound::File(wav_file.to_owned())).unwrap().samples.len());
+        assert_eq!(2, errors.len());
+        assert!(errors[0].contains("supported formats are mp3, ogg, wav and flac"));
+        assert!(errors[1].ends_with("does not exist"));
+    }
+
+    #[test]
+    fn test_recorded_sounds_for_game_log() {
+        let dir = TestDir::new("recorded_sounds");
+        std::fs::create_dir_all(dir.join("Gamelogs")).unwrap();
+        std::fs::create_dir_all(dir.join("Chatlogs")).unwrap();
+        let wav_file = write_wav(&dir.join("faction.wav"));
+        let game_log = dir.join("Gamelogs").join("20230918_120000_123456789.txt");
+        std::fs::write(&game_log, "------------------------------------------------------------\r\n  Gamelog\r\n  Listener: Alt A\r\n  Session Started: 2023.09.18 12:00:00\r\n------------------------------------------------------------\r\n").unwrap();
+
+        let cfg = BurritoCfg {
+            log_dir: dir.to_string_lossy().into_owned(),
+            alert_config: AlertConfig {
+                rules: vec![AlertRule {
+                    name: "Faction spawn".to_owned(),
+                    condition: AlertCondition { event_types: vec![EventKind::FactionSpawn], ..Default::default() },
+                    actions: vec![AlertAction::sound(&wav_file)],
+                    priority: 0,
+                    stop_processing: false,
+                    escalation: None,
+                }],
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let mut log_watcher = LogWatcher::new(SystemContext::default(), cfg.clone(), BurritoData::default(), SystemMap::default());
+        log_watcher.init();
+
+        let played = Arc::new(Mutex::new(vec![]));
+        let recorder = played.clone();
+        let (sounds, errors) = SoundCache::load(&cfg.alert_config.get_sound_files());
+        assert!(errors.is_empty());
+        let engine = AudioEngine::with_backend(&cfg.sound_config, sounds, move || Box::new(RecordingBackend::new(recorder)));
+        let mut notifiers = Notifiers::new();
+        notifiers.register("audio", Box::new(AudioNotifier::new(engine)));
+
+        let mut f = OpenOptions::new().append(true).open(&game_log).unwrap();
+        write!(f, "[ 2023.09.18 12:00:05 ] (combat) <b>523</b> from Dread Guristas Eliminator - Hits\r\n").unwrap();
+        write!(f, "[ 2023.09.18 12:00:06 ] (combat) <b>112</b> from Guristas Eliminator - Hits\r\n").unwrap();
+        for event in log_watcher.get_events() {
+            notifiers.notify(&event, &cfg.alert_config.get_actions(&event, &cfg.character_tags));
+        }
+
+        let start = Instant::now();
+        while played.lock().unwrap().is_empty() && start.elapsed() < Duration::from_secs(5) {
+            thread::sleep(Duration::from_millis(10));
+        }
+        thread::sleep(Duration::from_millis(100));
+        let played: Vec<Sound> = played.lock().unwrap().iter().map(|r| r.sound.clone()).collect();
+        assert_eq!(vec![Sound::File(wav_file)], played);
+    }
+
+    /// Waits until `played` holds `count` sounds, returns them
+    fn wait_for_played(played: &Arc<Mutex<Vec<SoundRequest>>>, count: usize) -> Vec<Sound> {
+        let start = Instant::now();
+        while played.lock().unwrap().len() < count && start.elapsed() < Duration::from_secs(5) {
+            thread::sleep(Duration::from_millis(10));
+        }
+        played.lock().unwrap().iter().map(|r| r.sound.clone()).collect()
+    }
+
+    #[test]
+    fn test_device_changed_while_idle() {
+        let played = Arc::new(Mutex::new(vec![]));
+        let devices = Arc::new(Mutex::new(vec!["Speakers".to_owned()]));
+        let (recorder, backend_devices) = (played.clone(), devices.clone());
+        let cfg = BurritoCfg::default();
+        let engine = AudioEngine::with_backend(&cfg.sound_config, SoundCache::default(), move || {
+            Box::new(RecordingBackend::with_devices(recorder, backend_devices, Duration::ZERO))
+        });
+        let tone = |tone: Tone| SoundRequest { sound: Sound::Tone(tone), volume: 100, priority: 0 };
+        engine.play(tone(Tone::Chirp)).unwrap();
+        wait_for_played(&played, 1);
+
+        // Unplugged while nothing plays, the next sound goes to the new device
+        *devices.lock().unwrap() = vec!["Headset".to_owned()];
+        engine.play(tone(Tone::Siren)).unwrap();
+        assert_eq!(vec![Sound::Tone(Tone::Chirp), Sound::Tone(Tone::Siren)], wait_for_played(&played, 2));
+        engine.finish();
+    }
+
+    #[test]
+    fn test_device_changed_while_playing() {
+        let played = Arc::new(Mutex::new(vec![]));
+        let devices = Arc::new(Mutex::new(vec!["Speakers".to_owned()]));
+        let (recorder, backend_devices) = (played.clone(), devices.clone());
+        let cfg = BurritoCfg::default();
+        let engine = AudioEngine::with_backend(&cfg.sound_config, SoundCache::default(), move || {
+            Box::new(RecordingBackend::with_devices(recorder, backend_devices, Duration::from_millis(500)))
+        });
+        engine.play(SoundRequest { sound: Sound::Tone(Tone::Siren), volume: 100, priority: 0 }).unwrap();
+        wait_for_played(&played, 1);
+
+        *devices.lock().unwrap() = vec!["Headset".to_owned()];
+        // The interrupted sound plays again on the new device
+        assert_eq!(vec![Sound::Tone(Tone::Siren), Sound::Tone(Tone::Siren)], wait_for_played(&played, 2));
+        engine.finish();
+    }
+
+    #[test]
+    fn test_tone_fallback() {
+        let played = Arc::new(Mutex::new(vec![]));
+        let recorder = played.clone();
+        let cfg = BurritoCfg::default();
+        let engine = AudioEngine::with_backend(&cfg.sound_config, SoundCache::default(), move || Box::new(RecordingBackend::new(recorder)));
+        let mut uut = AudioNotifier::new(engine);
+        let event = LogEvent::test(EventType::FactionSpawn)
+            .with_trigger("Dread Guristas Eliminator")
+            .with_message("Dread Guristas spawn!");
+        // The sounds directory was never set up
+        assert_eq!(Ok(()), uut.notify(&event, &AlertAction::sound("/nonexistent/faction_spawn.mp3")));
+        assert_eq!(Ok(()), uut.notify(&event, &AlertAction::tone(Tone::Siren)));
+
+        let start = Instant::now();
+        while played.lock().unwrap().len() < 2 && start.elapsed() < Duration::from_secs(5) {
+            thread::sleep(Duration::from_millis(10));
+        }
+        let played: Vec<Sound> = played.lock().unwrap().iter().map(|r| r.sound.clone()).collect();
+        assert_eq!(vec![Sound::Tone(Tone::TripleBeep), Sound::Tone(Tone::Siren)], played);
+    }
+}
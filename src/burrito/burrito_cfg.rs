@@ -1,8 +1,8 @@
-use std::collections::{BTreeSet, HashSet};
+use std::collections::{BTreeMap, BTreeSet, HashSet};
 
 use serde_derive::{Deserialize, Serialize};
 
-use super::{log_watcher::EventType, serde_utils, utils, log_watcher::IntelChannel};
+use super::{alert::OutputFormat, alert_rules::AlertConfig, log_watcher::EventType, serde_utils, utils, log_watcher::IntelChannel, mutes::QuietHours};
 
 #[derive(Clone, Deserialize, Serialize)]
 pub struct BurritoCfg {
@@ -13,88 +13,247 @@ pub struct BurritoCfg {
     #[serde(default)]
     pub game_log_alert_cd_ms: u64,
     #[serde(default)]
+    pub game_log_alert_mode: GameLogAlertMode,
+    #[serde(default = "default_site_npc_timeout_ms")]
+    pub site_npc_timeout_ms: u64,
+    #[serde(default)]
     pub hide_chat_messages: bool,
     #[serde(default)]
     pub hide_out_of_range_events: bool,
     #[serde(default)]
     pub recent_post_cache_ttl_ms: i64,
+    #[serde(default = "AlertConfig::missing")]
+    pub alert_config: AlertConfig,
     #[serde(default)]
     pub sound_config: AudioAlertConfig,
     #[serde(default)]
+    pub damage_alert_config: DamageAlertConfig,
+    /// Tags for each character, e.g. `"ratting"`, used to select which alerts apply to it
+    #[serde(default)]
+    pub character_tags: BTreeMap<String, BTreeSet<String>>,
+    #[serde(default)]
     pub text_channel_config: TextChannelConfig,
+    #[serde(default)]
+    pub ratting_config: RattingConfig,
+    #[serde(default)]
+    pub client_monitor_config: ClientMonitorConfig,
+    #[serde(default)]
+    pub journal_config: JournalConfig,
+    #[serde(default)]
+    pub api_config: ApiConfig,
+    /// How events are printed, `--output` overrides it
+    #[serde(default)]
+    pub output_format: OutputFormat,
+    /// Daily periods in which some or all alerts are muted
+    #[serde(default)]
+    pub quiet_hours: Vec<QuietHours>,
 }
 
 impl BurritoCfg {
 
     pub fn load_from_file() -> Self {
-        serde_utils::read_or_create_default_data_struct("", "burrito.cfg")
+        let mut cfg: Self = serde_utils::read_or_create_default_data_struct("", "burrito.cfg");
+        if cfg.alert_config.needs_migration {
+            cfg.migrate_audio_alerts();
+            cfg.save();
+        }
+        cfg
+    }
+
+    /// Reads burrito.cfg again while running, without creating or rewriting it
+    pub fn read_from_file() -> Result<Self, String> {
+        let path = get_path();
+        let text = std::fs::read_to_string(&path).map_err(|e| format!("Unable to read {path}: {e}"))?;
+        let mut cfg: Self = serde_json::from_str(&text).map_err(|e| format!("Invalid {path}: {e}"))?;
+        if cfg.alert_config.needs_migration {
+            cfg.migrate_audio_alerts();
+        }
+        Ok(cfg)
+    }
+
+    /// Checks the settings that parsing alone does not
+    pub fn validate(&self) -> Result<(), String> {
+        for quiet_hours in &self.quiet_hours {
+            quiet_hours.validate().map_err(|e| format!("Invalid quiet_hours in burrito.cfg: {e}"))?;
+        }
+        self.alert_config.validate().map_err(|e| format!("{e} in burrito.cfg"))?;
+        Ok(())
+    }
+
+    pub fn save(&self) {
+        serde_utils::write_data_struct("", "burrito.cfg", self);
+    }
+
+    /// Replaces the `audio_alerts` of configs from before alert rules existed
+    /// with equivalent rules
+    pub fn migrate_audio_alerts(&mut self) {
+        if self.sound_config.audio_alerts.is_empty() {
+            self.alert_config = AlertConfig::from_audio_alerts(default_audio_alerts().iter());
+        }
+        else {
+            eprintln!("Converting audio_alerts in burrito.cfg to alert rules");
+            self.alert_config = AlertConfig::from_audio_alerts(self.sound_config.audio_alerts.iter());
+            self.sound_config.audio_alerts.clear();
+        }
     }
 
 }
 
+pub fn get_path() -> String {
+    format!("{}burrito.cfg", utils::get_burrito_dir())
+}
+
 impl Default for BurritoCfg {
     fn default() -> Self {
         Self {
             log_dir: format!("{}/Documents/Eve/logs/", utils::get_home_dir()).to_owned(),
             log_update_interval_ms: 500,
             game_log_alert_cd_ms: 5000,
+            game_log_alert_mode: Default::default(),
+            site_npc_timeout_ms: default_site_npc_timeout_ms(),
             hide_chat_messages: false,
             hide_out_of_range_events: false,
             recent_post_cache_ttl_ms: 30000,
+            alert_config: AlertConfig::from_audio_alerts(default_audio_alerts().iter()),
             sound_config: Default::default(),
+            damage_alert_config: Default::default(),
+            character_tags: BTreeMap::new(),
             text_channel_config: Default::default(),
+            ratting_config: Default::default(),
+            client_monitor_config: Default::default(),
+            journal_config: Default::default(),
+            api_config: Default::default(),
+            output_format: Default::default(),
+            quiet_hours: vec![],
         }
     }
 }
 
+fn default_site_npc_timeout_ms() -> u64 {
+    120000
+}
+
+/// How repeated game log alerts for the same NPC are suppressed
+#[derive(Clone, Debug, Default, Deserialize, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize)]
+pub enum GameLogAlertMode {
+    /// Alert at most once every `game_log_alert_cd_ms` per character, event type and NPC
+    #[default]
+    Cooldown,
+    /// Alert on the first sighting of an NPC and stay quiet until it is gone
+    ///
+    /// An NPC is gone once a bounty tick follows a combat line naming it, or
+    /// after no combat line has named it for `site_npc_timeout_ms` (0 disables
+    /// the timeout).
+    OncePerSite,
+}
+
 #[derive(Clone, Deserialize, Serialize)]
 pub struct AudioAlertConfig {
+    /// Sounds for event types, from before alert rules existed
+    ///
+    /// These are converted to rules in `alert_config` when the configuration is
+    /// loaded.
     #[serde(default)]
     pub audio_alerts: BTreeSet<AudioAlert>,
+    /// A sound that is requested again within this time is only played once
+    #[serde(default = "default_dedup_window_ms")]
+    pub dedup_window_ms: u64,
+    /// Name, or part of the name, of the output device to play sounds on
+    ///
+    /// The default device is used if unset or if no device matches.
+    #[serde(default)]
+    pub audio_device: Option<String>,
+    #[serde(default)]
+    pub audio_backend: AudioBackendKind,
 }
 
 impl Default for AudioAlertConfig {
     fn default() -> Self {
-        let mut def = Self {
+        Self {
             audio_alerts: BTreeSet::new(),
-        };
-        let burrito_dir = utils::get_burrito_dir();
-        let mut def_neutral_file = burrito_dir.clone();
-        def_neutral_file.push_str("sounds/neut_in_range.mp3");
-        def.audio_alerts.insert(AudioAlert {
-            trigger: EventType::RangeOfSystem(5),
-            sound_file: def_neutral_file,
-        });
-        let mut def_faction_file = burrito_dir.clone();
-        def_faction_file.push_str("sounds/faction_spawn.mp3");
-        def.audio_alerts.insert(AudioAlert {
-            trigger: EventType::FactionSpawn,
-            sound_file: def_faction_file,
-        });
-        let mut def_special_spawn = burrito_dir.clone();
-        def_special_spawn.push_str("sounds/special_spawn.mp3");
-        def.audio_alerts.insert(AudioAlert {
-            trigger: EventType::DreadSpawn,
-            sound_file: def_special_spawn.clone(),
-        });
-        def.audio_alerts.insert(AudioAlert {
-            trigger: EventType::TitanSpawn,
-            sound_file: def_special_spawn.clone(),
-        });
-        def.audio_alerts.insert(AudioAlert {
-            trigger: EventType::OfficerSpawn,
-            sound_file: def_special_spawn,
-        });
-        def
+            dedup_window_ms: default_dedup_window_ms(),
+            audio_device: None,
+            audio_backend: Default::default(),
+        }
     }
 }
 
+fn default_dedup_window_ms() -> u64 {
+    2000
+}
+
+/// Where alert sounds are played
+#[derive(Clone, Debug, Default, Deserialize, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize)]
+pub enum AudioBackendKind {
+    /// An audio device if there is one, nowhere otherwise
+    #[default]
+    Auto,
+    /// An audio device
+    Device,
+    /// Nowhere, e.g. on a headless machine
+    Null,
+}
+
+/// Alerts that come with Burrito
+pub fn default_audio_alerts() -> BTreeSet<AudioAlert> {
+    let mut audio_alerts = BTreeSet::new();
+    let burrito_dir = utils::get_burrito_dir();
+    let mut def_neutral_file = burrito_dir.clone();
+    def_neutral_file.push_str("sounds/neut_in_range.mp3");
+    audio_alerts.insert(AudioAlert {
+        trigger: EventType::RangeOfSystem(5),
+        sound_file: def_neutral_file,
+    });
+    let mut def_faction_file = burrito_dir.clone();
+    def_faction_file.push_str("sounds/faction_spawn.mp3");
+    audio_alerts.insert(AudioAlert {
+        trigger: EventType::FactionSpawn,
+        sound_file: def_faction_file,
+    });
+    let mut def_special_spawn = burrito_dir.clone();
+    def_special_spawn.push_str("sounds/special_spawn.mp3");
+    audio_alerts.insert(AudioAlert {
+        trigger: EventType::DreadSpawn,
+        sound_file: def_special_spawn.clone(),
+    });
+    audio_alerts.insert(AudioAlert {
+        trigger: EventType::TitanSpawn,
+        sound_file: def_special_spawn.clone(),
+    });
+    audio_alerts.insert(AudioAlert {
+        trigger: EventType::OfficerSpawn,
+        sound_file: def_special_spawn,
+    });
+    audio_alerts
+}
+
 #[derive(Clone, Debug, Eq, Hash, Deserialize, Ord, PartialEq, PartialOrd, Serialize)]
 pub struct AudioAlert {
     pub trigger: EventType,
     pub sound_file: String,
 }
 
+#[derive(Clone, Default, Deserialize, Serialize)]
+pub struct DamageAlertConfig {
+    #[serde(default)]
+    pub damage_alerts: Vec<DamageAlert>,
+}
+
+/// Alert when the incoming DPS on a client goes past a threshold
+///
+/// Crossing the threshold produces a `DamageThreshold(dps)` event, which an
+/// `alert_config` rule matches with a `damage_threshold` condition.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct DamageAlert {
+    /// Incoming DPS that triggers the alert
+    pub dps: u32,
+    /// Window over which incoming damage is averaged
+    pub window_ms: u64,
+    /// Only apply to characters with this tag in `character_tags`
+    #[serde(default)]
+    pub character_tag: Option<String>,
+}
+
 #[derive(Clone, Deserialize, Serialize)]
 pub struct TextChannelConfig {
     #[serde(default)]
@@ -108,3 +267,110 @@ impl Default for TextChannelConfig {
         TextChannelConfig { text_channels: channels }
     }
 }
+
+/// Settings left out of `ratting_config` keep their defaults
+#[derive(Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct RattingConfig {
+    /// How often to print a bounty summary for every character (0 disables it)
+    pub summary_interval_ms: u64,
+    /// Alert when a character that was ratting gets no bounties for this long (0 disables it)
+    pub bounty_idle_alert_ms: u64,
+}
+
+impl Default for RattingConfig {
+    fn default() -> Self {
+        Self {
+            summary_interval_ms: 0,
+            bounty_idle_alert_ms: 600000,
+        }
+    }
+}
+
+/// Settings left out of `client_monitor_config` keep their defaults
+#[derive(Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct ClientMonitorConfig {
+    /// Alert when a character in space writes nothing to its game log for this long (0 disables it)
+    pub inactivity_alert_ms: u64,
+}
+
+impl Default for ClientMonitorConfig {
+    fn default() -> Self {
+        Self {
+            inactivity_alert_ms: 300000,
+        }
+    }
+}
+
+/// Settings left out of `journal_config` keep their defaults
+#[derive(Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct JournalConfig {
+    /// Write every event to `.burrito/journal/` for `burrito history`
+    pub enabled: bool,
+    /// Days of events to keep (0 keeps them forever)
+    pub retention_days: u32,
+}
+
+impl Default for JournalConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            retention_days: 30,
+        }
+    }
+}
+
+#[derive(Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct ApiConfig {
+    /// Serve the HTTP and WebSocket API on localhost
+    pub enabled: bool,
+    pub port: u16,
+    /// Number of events kept for `GET /api/events`
+    pub recent_events: usize,
+    /// Web pages other than localhost that may use the API, e.g. `https://overlay.example.com`
+    pub allowed_origins: Vec<String>,
+}
+
+impl Default for ApiConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            port: 7373,
+            recent_events: 100,
+            allowed_origins: vec![],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::BurritoCfg;
+
+    #[test]
+    fn test_load_old_cfg() {
+        // Settings missing from a burrito.cfg written by an older version get their defaults
+        let uut: BurritoCfg = serde_json::from_str(r#"{"log_dir": "/eve/logs", "game_log_alert_cd_ms": 5000}"#).unwrap();
+        assert_eq!(120000, uut.site_npc_timeout_ms);
+        let uut: BurritoCfg = serde_json::from_str(r#"{"sound_config": {"audio_alerts": []}}"#).unwrap();
+        assert_eq!(2000, uut.sound_config.dedup_window_ms);
+    }
+
+    #[test]
+    fn test_partial_sections() {
+        let uut: BurritoCfg = serde_json::from_str(r#"{"journal_config": {"enabled": true}}"#).unwrap();
+        assert!(uut.journal_config.enabled);
+        assert_eq!(30, uut.journal_config.retention_days);
+        let uut: BurritoCfg = serde_json::from_str(r#"{"api_config": {"enabled": true}}"#).unwrap();
+        assert!(uut.api_config.enabled);
+        assert_eq!(7373, uut.api_config.port);
+        assert_eq!(100, uut.api_config.recent_events);
+        let uut: BurritoCfg = serde_json::from_str(r#"{"ratting_config": {"summary_interval_ms": 3600000}}"#).unwrap();
+        assert_eq!(3600000, uut.ratting_config.summary_interval_ms);
+        assert_eq!(600000, uut.ratting_config.bounty_idle_alert_ms);
+        let uut: BurritoCfg = serde_json::from_str(r#"{"client_monitor_config": {}}"#).unwrap();
+        assert_eq!(300000, uut.client_monitor_config.inactivity_alert_ms);
+    }
+}
@@ -1,6 +1,8 @@
+use std::collections::BTreeMap;
+
 use serde::{Deserialize, Serialize};
 
-use super::serde_utils;
+use super::{serde_utils, utils};
 
 #[derive(Clone, Deserialize, Serialize)]
 pub struct BurritoData {
@@ -10,12 +12,32 @@ pub struct BurritoData {
     pub officer_npc_alerts: Vec<String>,
     #[serde(default)]
     pub special_npc_alerts: Vec<String>,
+    /// Volume in m3 of one unit of each ore
+    #[serde(default = "default_ore_volumes")]
+    pub ore_volumes: BTreeMap<String, f64>,
+    #[serde(default = "default_mining_hold_full_messages")]
+    pub mining_hold_full_messages: Vec<String>,
+    #[serde(default = "default_asteroid_depleted_messages")]
+    pub asteroid_depleted_messages: Vec<String>,
+    /// Game log messages written when a character docks
+    #[serde(default = "default_docked_messages")]
+    pub docked_messages: Vec<String>,
+    /// Game log messages that mean a character is in space
+    #[serde(default = "default_in_space_messages")]
+    pub in_space_messages: Vec<String>,
 }
 
 impl BurritoData {
     pub fn load_from_file() -> Self {
         serde_utils::read_or_create_default_data_struct("", "burrito.dat")
     }
+
+    /// Reads burrito.dat again while running, without creating or rewriting it
+    pub fn read_from_file() -> Result<Self, String> {
+        let path = format!("{}burrito.dat", utils::get_burrito_dir());
+        let text = std::fs::read_to_string(&path).map_err(|e| format!("Unable to read {path}: {e}"))?;
+        serde_json::from_str(&text).map_err(|e| format!("Invalid {path}: {e}"))
+    }
 }
 
 impl Default for BurritoData {
@@ -61,6 +83,117 @@ impl Default for BurritoData {
                 "Dreadnought".to_owned(),
                 "Titan".to_owned(),
             ].to_vec(),
+            ore_volumes: default_ore_volumes(),
+            mining_hold_full_messages: default_mining_hold_full_messages(),
+            asteroid_depleted_messages: default_asteroid_depleted_messages(),
+            docked_messages: default_docked_messages(),
+            in_space_messages: default_in_space_messages(),
         }
     }
 }
+
+fn default_ore_volumes() -> BTreeMap<String, f64> {
+    [
+        // Asteroid ores
+        ("Veldspar", 0.1),
+        ("Scordite", 0.15),
+        ("Pyroxeres", 0.3),
+        ("Plagioclase", 0.35),
+        ("Omber", 0.6),
+        ("Kernite", 1.2),
+        ("Jaspet", 2.0),
+        ("Hemorphite", 3.0),
+        ("Hedbergite", 3.0),
+        ("Gneiss", 5.0),
+        ("Dark Ochre", 8.0),
+        ("Spodumain", 16.0),
+        ("Crokite", 16.0),
+        ("Bistot", 16.0),
+        ("Arkonor", 16.0),
+        ("Mercoxit", 40.0),
+        // Moon ores
+        ("Bitumens", 10.0),
+        ("Coesite", 10.0),
+        ("Sylvite", 10.0),
+        ("Zeolites", 10.0),
+        ("Cobaltite", 10.0),
+        ("Euxenite", 10.0),
+        ("Scheelite", 10.0),
+        ("Titanite", 10.0),
+        ("Chromite", 10.0),
+        ("Otavite", 10.0),
+        ("Sperrylite", 10.0),
+        ("Vanadinite", 10.0),
+        ("Carnotite", 10.0),
+        ("Cinnabar", 10.0),
+        ("Pollucite", 10.0),
+        ("Zircon", 10.0),
+        ("Loparite", 10.0),
+        ("Monazite", 10.0),
+        ("Xenotime", 10.0),
+        ("Ytterbite", 10.0),
+        // Ice
+        ("Blue Ice", 1000.0),
+        ("Clear Icicle", 1000.0),
+        ("Glacial Mass", 1000.0),
+        ("White Glaze", 1000.0),
+        ("Dark Glitter", 1000.0),
+        ("Gelidus", 1000.0),
+        ("Glare Crust", 1000.0),
+        ("Krystallos", 1000.0),
+    ].into_iter().map(|(ore, volume)| (ore.to_owned(), volume)).collect()
+}
+
+fn default_mining_hold_full_messages() -> Vec<String> {
+    [
+        "cargo hold is full".to_owned(),
+        "ore hold is full".to_owned(),
+        "ore hold being full".to_owned(),
+        "cargo hold of your ship is full".to_owned(),
+    ].to_vec()
+}
+
+fn default_asteroid_depleted_messages() -> Vec<String> {
+    [
+        "asteroid is depleted".to_owned(),
+        "has been depleted".to_owned(),
+        "asteroid depleted".to_owned(),
+    ].to_vec()
+}
+
+fn default_docked_messages() -> Vec<String> {
+    [
+        "Requested to dock".to_owned(),
+    ].to_vec()
+}
+
+fn default_in_space_messages() -> Vec<String> {
+    [
+        "Undocking from".to_owned(),
+        "Jumping from".to_owned(),
+    ].to_vec()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::BurritoData;
+
+    #[test]
+    fn test_load_old_data_file() {
+        // burrito.dat from before mining was tracked
+        let text = r#"{
+            "faction_npc_alerts": ["Dark Blood"],
+            "officer_npc_alerts": ["Gotan Kreiss"],
+            "special_npc_alerts": ["Titan"]
+        }"#;
+        let uut: BurritoData = serde_json::from_str(text).unwrap();
+        let default = BurritoData::default();
+        assert_eq!(vec!["Dark Blood".to_owned()], uut.faction_npc_alerts);
+        assert_eq!(default.ore_volumes, uut.ore_volumes);
+        assert_eq!(Some(&0.1), uut.ore_volumes.get("Veldspar"));
+        assert_eq!(default.mining_hold_full_messages, uut.mining_hold_full_messages);
+        assert_eq!(default.asteroid_depleted_messages, uut.asteroid_depleted_messages);
+        assert_eq!(default.docked_messages, uut.docked_messages);
+        assert_eq!(default.in_space_messages, uut.in_space_messages);
+    }
+}
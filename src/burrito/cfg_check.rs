@@ -0,0 +1,191 @@
+use std::{fmt::Display, fs, path::Path};
+
+use serde::de::DeserializeOwned;
+
+use super::{audio::SoundCache, burrito_cfg::BurritoCfg, burrito_data::BurritoData, mutes::Mutes, systems::{self, SystemContext, SystemMap}};
+
+/// Something wrong with one of the files in `.burrito/`
+#[derive(Clone, Debug, PartialEq)]
+pub struct Problem {
+    pub file: String,
+    pub message: String,
+}
+
+impl Problem {
+    fn new(file: &str, message: impl Into<String>) -> Self {
+        Self { file: file.to_owned(), message: message.into() }
+    }
+}
+
+impl Display for Problem {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.file, self.message)
+    }
+}
+
+/// Describes where and why a JSON file failed to parse, e.g.
+/// ``line 3, column 5: expected `,` or `}` ``
+pub fn describe_json_error(e: &serde_json::Error) -> String {
+    if e.line() == 0 {
+        return e.to_string();
+    }
+    let text = e.to_string();
+    let position = format!(" at line {} column {}", e.line(), e.column());
+    let message = text.strip_suffix(&position).unwrap_or(&text);
+    format!("line {}, column {}: {message}", e.line(), e.column())
+}
+
+/// Checks every file in `dir`, the Burrito directory, and returns all problems found
+///
+/// Missing files are not problems, except `systems.json`, since Burrito creates
+/// the others with default values.
+pub fn check_dir(dir: &Path) -> Vec<Problem> {
+    let mut problems = vec![];
+    let cfg: Option<BurritoCfg> = parse_file(dir, "burrito.cfg", &mut problems);
+    if let Some(mut cfg) = cfg {
+        if cfg.alert_config.needs_migration {
+            cfg.migrate_audio_alerts();
+        }
+        problems.extend(check_cfg(&cfg));
+    }
+    else if !dir.join("burrito.cfg").exists() {
+        problems.extend(check_cfg(&BurritoCfg::default()));
+    }
+    _ = parse_file::<BurritoData>(dir, "burrito.dat", &mut problems);
+    _ = parse_file::<Mutes>(dir, "mutes.json", &mut problems);
+    let sys_map: Option<SystemMap> = parse_file(dir, "systems.json", &mut problems);
+    if sys_map.is_none() && !dir.join("systems.json").exists() {
+        problems.push(Problem::new("systems.json", "missing, download it as described in the README"));
+    }
+    let ctx: Option<SystemContext> = parse_file(dir, "ctx.json", &mut problems);
+    if let (Some(ctx), Some(sys_map)) = (ctx, sys_map) {
+        problems.extend(check_context(&ctx, &sys_map));
+    }
+    problems
+}
+
+/// Parses `dir/file`, adding a problem if it exists but is invalid
+fn parse_file<T: DeserializeOwned>(dir: &Path, file: &str, problems: &mut Vec<Problem>) -> Option<T> {
+    let path = dir.join(file);
+    if !path.exists() {
+        return None;
+    }
+    let text = match fs::read_to_string(&path) {
+        Ok(text) => text,
+        Err(e) => {
+            problems.push(Problem::new(file, format!("unable to read: {e}")));
+            return None;
+        },
+    };
+    serde_json::from_str(&text)
+        .map_err(|e| problems.push(Problem::new(file, describe_json_error(&e))))
+        .ok()
+}
+
+/// Settings of a parsed burrito.cfg that point at missing files or contradict each other
+pub fn check_cfg(cfg: &BurritoCfg) -> Vec<Problem> {
+    let problem = |message: String| Problem::new("burrito.cfg", message);
+    let mut problems = vec![];
+    if let Err(e) = cfg.validate() {
+        problems.push(problem(e));
+    }
+    let log_dir = Path::new(&cfg.log_dir);
+    let chat_log_dir = log_dir.join("Chatlogs");
+    if !log_dir.is_dir() {
+        problems.push(problem(format!("log_dir {} does not exist", cfg.log_dir)));
+    }
+    else {
+        for sub_dir in ["Chatlogs", "Gamelogs"] {
+            if !log_dir.join(sub_dir).is_dir() {
+                problems.push(problem(format!("log_dir {} has no {sub_dir} directory", cfg.log_dir)));
+            }
+        }
+    }
+    let (_, errors) = SoundCache::load(&cfg.alert_config.get_sound_files());
+    problems.extend(errors.into_iter().map(problem));
+    problems.extend(check_rules(cfg).into_iter().map(problem));
+    if let Ok(files) = fs::read_dir(&chat_log_dir) {
+        let file_names: Vec<String> = files
+            .filter_map(|file| file.ok())
+            .map(|file| file.file_name().to_string_lossy().into_owned())
+            .filter(|name| name.ends_with(".txt"))
+            .collect();
+        let mut channels: Vec<String> = cfg.text_channel_config.text_channels.iter()
+            .map(|channel| channel.get_channel())
+            .filter(|channel| !file_names.iter().any(|name| name.starts_with(channel.as_str())))
+            .collect();
+        channels.sort();
+        problems.extend(channels.into_iter().map(|channel| {
+            problem(format!("no chat logs for channel {channel} in {}", chat_log_dir.display()))
+        }));
+    }
+    problems
+}
+
+/// Alert rules that share a name or would alert twice for the same event
+fn check_rules(cfg: &BurritoCfg) -> Vec<String> {
+    let rules = &cfg.alert_config.rules;
+    let mut problems = vec![];
+    for (i, rule) in rules.iter().enumerate() {
+        let earlier = &rules[..i];
+        if !rule.name.is_empty() && earlier.iter().any(|other| other.name.eq_ignore_ascii_case(&rule.name)) {
+            problems.push(format!("more than one alert rule is named {}", rule.name));
+        }
+        if let Some(other) = earlier.iter().find(|other| other.condition == rule.condition && other.actions == rule.actions) {
+            problems.push(format!("alert rules {:?} and {:?} have the same condition and actions", other.name, rule.name));
+        }
+    }
+    problems
+}
+
+/// Watched systems in ctx.json that are not on the map
+pub fn check_context(ctx: &SystemContext, sys_map: &SystemMap) -> Vec<Problem> {
+    let mut unknown: Vec<&String> = ctx.get_current_systems().iter()
+        .filter(|name| systems::get_system_id(name, sys_map).is_none())
+        .collect();
+    unknown.sort();
+    unknown.into_iter()
+        .map(|name| Problem::new("ctx.json", format!("unknown system name: {name}")))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use crate::burrito::utils::TestDir;
+
+    use super::{check_dir, Problem};
+
+    #[test]
+    fn test_check_dir() {
+        let dir = TestDir::new("cfg_check");
+        let log_dir = dir.join("logs");
+        fs::create_dir_all(log_dir.join("Chatlogs")).unwrap();
+        fs::write(log_dir.join("Chatlogs").join("delve.imperium_20231001_120000.txt"), "").unwrap();
+        let cfg = serde_json::json!({
+            "log_dir": log_dir,
+            "text_channel_config": {"text_channels": [{"Custom": {"channel": "delve.imperium"}}, "East"]},
+            "alert_config": {"rules": [
+                {"name": "Spawns", "condition": {"event_types": ["FactionSpawn"]}, "actions": ["Print"]},
+                {"name": "spawns", "condition": {"event_types": ["FactionSpawn"]}, "actions": [{"Sound": {"file": "/nowhere/alarm.mp3"}}]},
+                {"name": "Copy", "condition": {"event_types": ["FactionSpawn"]}, "actions": ["Print"]},
+            ]},
+        });
+        fs::write(dir.join("burrito.cfg"), serde_json::to_string_pretty(&cfg).unwrap()).unwrap();
+        fs::write(dir.join("burrito.dat"), "{\n  \"faction_npc_alerts\": [\"Dark Blood\",]\n}").unwrap();
+        fs::write(dir.join("systems.json"), "{\"systems\": {}}").unwrap();
+        fs::write(dir.join("ctx.json"), "{\"current_systems\": [\"Jita\"]}").unwrap();
+
+        let problems: Vec<String> = check_dir(&dir).iter().map(Problem::to_string).collect();
+        assert_eq!(vec![
+            format!("burrito.cfg: log_dir {} has no Gamelogs directory", log_dir.display()),
+            "burrito.cfg: Sound file /nowhere/alarm.mp3 does not exist".to_owned(),
+            "burrito.cfg: more than one alert rule is named spawns".to_owned(),
+            "burrito.cfg: alert rules \"Spawns\" and \"Copy\" have the same condition and actions".to_owned(),
+            format!("burrito.cfg: no chat logs for channel east.imperium in {}", log_dir.join("Chatlogs").display()),
+            "burrito.dat: line 2, column 39: trailing comma".to_owned(),
+            "ctx.json: unknown system name: Jita".to_owned(),
+        ], problems);
+    }
+}
@@ -0,0 +1,176 @@
+use std::collections::BTreeSet;
+
+use chrono::{DateTime, Utc};
+
+use super::{alert_rules::AlertAction, log_watcher::{EventType, LogEvent}};
+
+/// Hostile reports that arrived close together
+#[derive(Clone, Debug)]
+struct Burst {
+    /// Nearest report so far, used for the summary
+    nearest_event: LogEvent,
+    nearest_actions: Vec<AlertAction>,
+    nearest_jumps: u32,
+    reports: usize,
+    systems: BTreeSet<String>,
+    last_report: DateTime<Utc>,
+}
+
+impl Burst {
+    fn new(event: &LogEvent, actions: &[AlertAction], jumps: u32, now: DateTime<Utc>) -> Self {
+        Self {
+            nearest_event: event.clone(),
+            nearest_actions: actions.to_vec(),
+            nearest_jumps: jumps,
+            reports: 1,
+            systems: BTreeSet::from([event.details.reported_system.to_owned()]),
+            last_report: now,
+        }
+    }
+
+    /// Summary alert for the burst, or `None` if there was only one report
+    ///
+    /// The summary runs the actions of the nearest report except for sounds,
+    /// since the burst has already been heard.
+    fn into_summary(self) -> Option<(LogEvent, Vec<AlertAction>)> {
+        if self.reports < 2 {
+            return None;
+        }
+        let mut event = self.nearest_event;
+        event.message = format!(
+            "{} reports, nearest {} jumps ({}), {} systems",
+            self.reports,
+            self.nearest_jumps,
+            event.details.reported_system,
+            self.systems.len(),
+        );
+        event.time = self.last_report;
+        let actions = self.nearest_actions.into_iter()
+            .filter(|action| !matches!(action, AlertAction::Sound { .. } | AlertAction::Tone { .. }))
+            .collect();
+        Some((event, actions))
+    }
+}
+
+/// Merges bursts of hostile reports into one alert
+///
+/// The first report of a burst alerts right away. Later reports only alert if
+/// they are closer than every report before them. Once no report arrived for
+/// the coalescing window, a summary of the burst is available.
+#[derive(Clone, Debug, Default)]
+pub struct AlertCoalescer {
+    window_ms: u64,
+    burst: Option<Burst>,
+    /// Summary of a burst that ended while a new one started
+    pending: Option<(LogEvent, Vec<AlertAction>)>,
+}
+
+impl AlertCoalescer {
+    pub fn new(window_ms: u64) -> Self {
+        Self {
+            window_ms,
+            burst: None,
+            pending: None,
+        }
+    }
+
+    fn is_expired(&self, burst: &Burst, now: DateTime<Utc>) -> bool {
+        (now - burst.last_report).num_milliseconds() > self.window_ms as i64
+    }
+
+    /// Adds an event that matched alert rules, returns whether it should alert now
+    pub fn add(&mut self, event: &LogEvent, actions: &[AlertAction], now: DateTime<Utc>) -> bool {
+        let jumps = match event.event_type {
+            EventType::RangeOfSystem(jumps) if self.window_ms > 0 => jumps,
+            _ => return true,
+        };
+        match self.burst.take() {
+            Some(mut burst) if !self.is_expired(&burst, now) => {
+                burst.reports += 1;
+                burst.systems.insert(event.details.reported_system.to_owned());
+                burst.last_report = now;
+                let closer = jumps < burst.nearest_jumps;
+                if closer {
+                    burst.nearest_event = event.clone();
+                    burst.nearest_actions = actions.to_vec();
+                    burst.nearest_jumps = jumps;
+                }
+                self.burst = Some(burst);
+                closer
+            },
+            expired => {
+                if let Some(burst) = expired {
+                    self.pending = burst.into_summary();
+                }
+                self.burst = Some(Burst::new(event, actions, jumps, now));
+                true
+            },
+        }
+    }
+
+    /// Returns the summary of a burst that has ended, if there is one
+    pub fn get_summary(&mut self, now: DateTime<Utc>) -> Option<(LogEvent, Vec<AlertAction>)> {
+        if let Some(summary) = self.pending.take() {
+            return Some(summary);
+        }
+        match self.burst.take() {
+            Some(burst) if self.is_expired(&burst, now) => burst.into_summary(),
+            burst => {
+                self.burst = burst;
+                None
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{TimeZone, Utc};
+
+    use crate::burrito::{alert_rules::AlertAction, log_watcher::{EventType, LogEvent}};
+
+    use super::AlertCoalescer;
+
+    fn event(jumps: u32, system: &str) -> LogEvent {
+        LogEvent::test(EventType::RangeOfSystem(jumps))
+            .with_trigger(format!("Reporter > {system} +5"))
+            .with_message(format!("Hostiles {jumps} jumps away from 1DQ1-A!"))
+            .with_system(system)
+    }
+
+    #[test]
+    fn test_coalesce_burst() {
+        let at = |ms: i64| Utc.timestamp_millis_opt(ms).unwrap();
+        let actions = vec![AlertAction::Print, AlertAction::sound("hostile.mp3")];
+        let mut uut = AlertCoalescer::new(5000);
+
+        assert!(uut.add(&event(4, "X"), &actions, at(0)));
+        assert!(!uut.add(&event(5, "Y"), &actions, at(1000)));
+        assert!(!uut.add(&event(4, "X"), &actions, at(2000)));
+        // Follow-up when the hostiles get closer
+        assert!(uut.add(&event(2, "Z"), &actions, at(3000)));
+        assert!(!uut.add(&event(3, "Y"), &actions, at(7000)));
+        assert!(uut.get_summary(at(12000)).is_none());
+
+        let (summary, summary_actions) = uut.get_summary(at(12001)).unwrap();
+        assert_eq!("5 reports, nearest 2 jumps (Z), 3 systems", summary.message);
+        assert_eq!(vec![AlertAction::Print], summary_actions);
+        assert!(uut.get_summary(at(20000)).is_none());
+
+        // A single report needs no summary
+        assert!(uut.add(&event(4, "X"), &actions, at(30000)));
+        assert!(uut.get_summary(at(40000)).is_none());
+
+        // A new burst that starts before the summary was taken keeps it
+        assert!(uut.add(&event(4, "X"), &actions, at(50000)));
+        assert!(!uut.add(&event(4, "X"), &actions, at(51000)));
+        assert!(uut.add(&event(4, "X"), &actions, at(60000)));
+        assert_eq!("2 reports, nearest 4 jumps (X), 1 systems", uut.get_summary(at(60000)).unwrap().0.message);
+
+        // Other events and a window of 0 always alert
+        assert!(uut.add(&LogEvent { event_type: EventType::FactionSpawn, ..event(0, "X") }, &actions, at(60001)));
+        let mut uut = AlertCoalescer::new(0);
+        assert!(uut.add(&event(4, "X"), &actions, at(0)));
+        assert!(uut.add(&event(4, "X"), &actions, at(0)));
+    }
+}
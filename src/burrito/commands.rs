@@ -0,0 +1,88 @@
+use std::{io::BufRead, sync::mpsc::{self, Receiver, Sender}, thread};
+
+/// A command for a running Burrito instance
+#[derive(Clone, Debug, PartialEq)]
+pub enum Command {
+    /// Acknowledge one repeating alert, or all of them if no id is given
+    Acknowledge(Option<u32>),
+    /// Mute alerts, with the arguments of `burrito mute`
+    Mute(Vec<String>),
+    /// Remove every mute, or the mutes for a category
+    Unmute(Option<String>),
+    ListMutes,
+    WatchSystem(String),
+    UnwatchSystem(String),
+    WatchCharacter(String),
+    UnwatchCharacter(String),
+    /// Reload burrito.cfg, burrito.dat and ctx.json
+    Reload,
+    /// List the logs being read
+    ListReaders,
+}
+
+impl Command {
+    /// Parses a command as typed into the terminal
+    ///
+    /// An empty line acknowledges every alert, so pressing Enter is enough.
+    pub fn parse(line: &str) -> Result<Self, String> {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        match words.as_slice() {
+            [] | ["ack"] => Ok(Command::Acknowledge(None)),
+            ["ack", id] => id.parse()
+                .map(|id| Command::Acknowledge(Some(id)))
+                .map_err(|_| format!("Invalid alert id: {id}")),
+            ["mute", "list"] | ["mutes"] => Ok(Command::ListMutes),
+            ["mute", args @ ..] => Ok(Command::Mute(args.iter().map(|a| a.to_string()).collect())),
+            ["unmute"] => Ok(Command::Unmute(None)),
+            ["unmute", category] => Ok(Command::Unmute(Some(category.to_string()))),
+            ["watch", "system", name @ ..] if !name.is_empty() => Ok(Command::WatchSystem(name.join(" "))),
+            ["unwatch", "system", name @ ..] if !name.is_empty() => Ok(Command::UnwatchSystem(name.join(" "))),
+            ["watch", "character", name @ ..] if !name.is_empty() => Ok(Command::WatchCharacter(name.join(" "))),
+            ["unwatch", "character", name @ ..] if !name.is_empty() => Ok(Command::UnwatchCharacter(name.join(" "))),
+            ["reload"] => Ok(Command::Reload),
+            ["readers"] => Ok(Command::ListReaders),
+            _ => Err(format!("Unrecognized command: {}", line.trim())),
+        }
+    }
+}
+
+/// Reads commands typed into the terminal Burrito runs in
+///
+/// Lines are read on a background thread and handed over as they arrive. The
+/// thread ends when stdin is closed.
+pub fn spawn_stdin_reader() -> Receiver<String> {
+    let (sender, receiver): (Sender<String>, Receiver<String>) = mpsc::channel();
+    thread::spawn(move || {
+        for line in std::io::stdin().lock().lines() {
+            match line {
+                Ok(line) => {
+                    if sender.send(line).is_err() {
+                        return;
+                    }
+                },
+                Err(_) => return,
+            }
+        }
+    });
+    receiver
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Command;
+
+    #[test]
+    fn test_parse_command() {
+        assert_eq!(Ok(Command::Acknowledge(None)), Command::parse(""));
+        assert_eq!(Ok(Command::Acknowledge(None)), Command::parse(" ack "));
+        assert_eq!(Ok(Command::Acknowledge(Some(3))), Command::parse("ack 3"));
+        assert!(Command::parse("ack three").is_err());
+        assert_eq!(Ok(Command::Mute(vec!["spawn".to_owned(), "30m".to_owned()])), Command::parse("mute spawn 30m"));
+        assert_eq!(Ok(Command::ListMutes), Command::parse("mute list"));
+        assert_eq!(Ok(Command::Unmute(Some("spawn".to_owned()))), Command::parse("unmute spawn"));
+        assert_eq!(Ok(Command::WatchCharacter("Bad Guy".to_owned())), Command::parse("watch character Bad Guy"));
+        assert!(Command::parse("watch system").is_err());
+        assert_eq!(Ok(Command::Reload), Command::parse("reload"));
+        assert!(Command::parse("dance").is_err());
+    }
+}
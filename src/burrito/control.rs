@@ -0,0 +1,298 @@
+use std::{io::{BufRead, BufReader, Read, Write}, path::{Path, PathBuf}, sync::mpsc::{self, Receiver, Sender}, time::Duration};
+
+use serde_derive::{Deserialize, Serialize};
+
+use super::commands::Command;
+
+/// How long a connection waits for the main loop to run its command
+const REPLY_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Answer to a command sent to the control socket, one JSON line
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct ControlResponse {
+    pub ok: bool,
+    pub message: String,
+}
+
+impl From<Result<String, String>> for ControlResponse {
+    fn from(result: Result<String, String>) -> Self {
+        match result {
+            Ok(message) => Self { ok: true, message },
+            Err(message) => Self { ok: false, message },
+        }
+    }
+}
+
+/// A command received by the control socket, waiting for the main loop
+pub struct ControlRequest {
+    pub command: Command,
+    reply: Sender<Result<String, String>>,
+}
+
+impl ControlRequest {
+    /// Sends the outcome of the command back to the client
+    pub fn reply(self, result: Result<String, String>) {
+        _ = self.reply.send(result);
+    }
+}
+
+/// Control socket of a running instance, used by `burrito ctl`
+///
+/// This is a Unix domain socket, or a named pipe on Windows. Each connection
+/// sends one command line, in the syntax of the commands typed into the
+/// terminal, and gets one `ControlResponse` back. Connections are served on
+/// background threads, but the commands are run by the main loop.
+pub struct ControlServer {
+    address: PathBuf,
+    requests: Receiver<ControlRequest>,
+}
+
+impl ControlServer {
+    /// Listens on `get_default_address()`
+    pub fn start() -> Result<Self, String> {
+        Self::start_at(get_default_address())
+    }
+
+    /// Listens on `address`, unless another instance already does
+    pub fn start_at(address: PathBuf) -> Result<Self, String> {
+        let (sender, requests) = mpsc::channel();
+        platform::listen(&address, sender)?;
+        Ok(Self {
+            address,
+            requests,
+        })
+    }
+
+    pub fn get_address(&self) -> &Path {
+        &self.address
+    }
+
+    /// Commands received since the last call, each of which needs a reply
+    pub fn get_requests(&self) -> Vec<ControlRequest> {
+        self.requests.try_iter().collect()
+    }
+}
+
+impl Drop for ControlServer {
+    fn drop(&mut self) {
+        platform::remove(&self.address);
+    }
+}
+
+/// `.burrito/control.sock`, or the pipe `\\.\pipe\burrito-control` on Windows
+pub fn get_default_address() -> PathBuf {
+    platform::get_default_address()
+}
+
+/// Sends a command line to the instance listening on `address` and returns its answer
+pub fn send_command(address: &Path, line: &str) -> Result<String, String> {
+    let mut stream = platform::connect(address)
+        .map_err(|e| format!("No running Burrito found at {}: {e}", address.display()))?;
+    writeln!(stream, "{}", line.trim()).map_err(|e| format!("Unable to send the command: {e}"))?;
+    let mut text = String::new();
+    stream.read_to_string(&mut text).map_err(|e| format!("No answer from Burrito: {e}"))?;
+    let response: ControlResponse = serde_json::from_str(&text).map_err(|e| format!("Invalid answer from Burrito: {e}"))?;
+    match response.ok {
+        true => Ok(response.message),
+        false => Err(response.message),
+    }
+}
+
+/// Reads the command of a connection, waits for the main loop and writes its answer
+fn handle_connection<S: Read + Write>(stream: S, requests: &Sender<ControlRequest>) -> S {
+    let mut reader = BufReader::new(stream);
+    let mut line = String::new();
+    let result = match reader.read_line(&mut line) {
+        // Closed without sending anything, e.g. another instance checking whether this one runs
+        Ok(0) => return reader.into_inner(),
+        Ok(_) => run_command(&line, requests),
+        Err(e) => Err(format!("Unable to read the command: {e}")),
+    };
+    let mut stream = reader.into_inner();
+    let response = serde_json::to_string(&ControlResponse::from(result)).unwrap();
+    _ = writeln!(stream, "{response}");
+    _ = stream.flush();
+    stream
+}
+
+fn run_command(line: &str, requests: &Sender<ControlRequest>) -> Result<String, String> {
+    let command = Command::parse(line)?;
+    let (reply, replies) = mpsc::channel();
+    requests.send(ControlRequest { command, reply }).map_err(|_| "Burrito is shutting down".to_owned())?;
+    replies.recv_timeout(REPLY_TIMEOUT).map_err(|_| "Burrito did not run the command in time".to_owned())?
+}
+
+#[cfg(unix)]
+mod platform {
+    use std::{io, os::unix::net::{UnixListener, UnixStream}, path::{Path, PathBuf}, sync::mpsc::Sender, thread};
+
+    use crate::burrito::utils;
+
+    use super::{handle_connection, ControlRequest, REPLY_TIMEOUT};
+
+    pub fn get_default_address() -> PathBuf {
+        Path::new(&utils::get_burrito_dir()).join("control.sock")
+    }
+
+    pub fn listen(address: &Path, requests: Sender<ControlRequest>) -> Result<(), String> {
+        if address.exists() {
+            if UnixStream::connect(address).is_ok() {
+                return Err(format!("Another Burrito is already listening on {}", address.display()));
+            }
+            // Left behind by an instance that did not shut down cleanly
+            _ = std::fs::remove_file(address);
+        }
+        let listener = UnixListener::bind(address)
+            .map_err(|e| format!("Unable to create the control socket {}: {e}", address.display()))?;
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let requests = requests.clone();
+                thread::spawn(move || handle_connection(stream, &requests));
+            }
+        });
+        Ok(())
+    }
+
+    pub fn connect(address: &Path) -> io::Result<UnixStream> {
+        let stream = UnixStream::connect(address)?;
+        stream.set_read_timeout(Some(REPLY_TIMEOUT * 2))?;
+        Ok(stream)
+    }
+
+    pub fn remove(address: &Path) {
+        _ = std::fs::remove_file(address);
+    }
+}
+
+#[cfg(windows)]
+mod platform {
+    use std::{fs::{File, OpenOptions}, io, iter, os::windows::{ffi::OsStrExt, io::{FromRawHandle, RawHandle}}, path::{Path, PathBuf}, ptr, sync::mpsc::Sender, thread};
+
+    use windows_sys::Win32::{
+        Foundation::{CloseHandle, GetLastError, ERROR_PIPE_CONNECTED, HANDLE, INVALID_HANDLE_VALUE},
+        Storage::FileSystem::{FILE_FLAG_FIRST_PIPE_INSTANCE, PIPE_ACCESS_DUPLEX},
+        System::Pipes::{ConnectNamedPipe, CreateNamedPipeW, PIPE_READMODE_BYTE, PIPE_REJECT_REMOTE_CLIENTS, PIPE_TYPE_BYTE, PIPE_UNLIMITED_INSTANCES, PIPE_WAIT},
+    };
+
+    use crate::burrito::diagnostics;
+
+    use super::{handle_connection, ControlRequest};
+
+    const BUFFER_SIZE: u32 = 4096;
+
+    pub fn get_default_address() -> PathBuf {
+        PathBuf::from(r"\\.\pipe\burrito-control")
+    }
+
+    /// Creates an instance of the pipe for the next client to connect to
+    fn create_pipe(name: &[u16], first: bool) -> io::Result<HANDLE> {
+        let first_flag = if first { FILE_FLAG_FIRST_PIPE_INSTANCE } else { 0 };
+        let handle = unsafe {
+            CreateNamedPipeW(
+                name.as_ptr(),
+                PIPE_ACCESS_DUPLEX | first_flag,
+                PIPE_TYPE_BYTE | PIPE_READMODE_BYTE | PIPE_WAIT | PIPE_REJECT_REMOTE_CLIENTS,
+                PIPE_UNLIMITED_INSTANCES,
+                BUFFER_SIZE,
+                BUFFER_SIZE,
+                0,
+                ptr::null(),
+            )
+        };
+        match handle {
+            INVALID_HANDLE_VALUE => Err(io::Error::last_os_error()),
+            handle => Ok(handle),
+        }
+    }
+
+    /// Waits for a client, returns whether one connected
+    fn wait_for_client(handle: HANDLE) -> bool {
+        unsafe { ConnectNamedPipe(handle, ptr::null_mut()) != 0 || GetLastError() == ERROR_PIPE_CONNECTED }
+    }
+
+    pub fn listen(address: &Path, requests: Sender<ControlRequest>) -> Result<(), String> {
+        let name: Vec<u16> = address.as_os_str().encode_wide().chain(iter::once(0)).collect();
+        // Only the first instance may be created with FILE_FLAG_FIRST_PIPE_INSTANCE,
+        // so this fails if another Burrito owns the pipe
+        let mut handle = create_pipe(&name, true)
+            .map_err(|e| format!("Unable to create the control pipe {} (is another Burrito running?): {e}", address.display()))?;
+        thread::spawn(move || loop {
+            if wait_for_client(handle) {
+                let file = unsafe { File::from_raw_handle(handle as RawHandle) };
+                let requests = requests.clone();
+                thread::spawn(move || {
+                    let file = handle_connection(file, &requests);
+                    // Closing the pipe discards whatever the client has not read yet
+                    _ = file.sync_all();
+                });
+            }
+            else {
+                unsafe { CloseHandle(handle) };
+            }
+            handle = match create_pipe(&name, false) {
+                Ok(handle) => handle,
+                Err(e) => {
+                    diagnostics::report(format!("Control pipe stopped: {e}"));
+                    return;
+                },
+            };
+        });
+        Ok(())
+    }
+
+    pub fn connect(address: &Path) -> io::Result<File> {
+        OpenOptions::new().read(true).write(true).open(address)
+    }
+
+    /// The pipe goes away with its last handle
+    pub fn remove(_address: &Path) {}
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use std::{thread, time::{Duration, Instant}};
+
+    use crate::burrito::{commands::Command, utils::TestDir};
+
+    use super::{send_command, ControlServer};
+
+    #[test]
+    fn test_control_socket() {
+        let dir = TestDir::new("control");
+        let address = dir.join("control.sock");
+        let uut = ControlServer::start_at(address.clone()).unwrap();
+        assert!(ControlServer::start_at(address.clone()).is_err());
+
+        let client_address = address.clone();
+        let client = thread::spawn(move || {
+            (
+                send_command(&client_address, "watch system Jita"),
+                send_command(&client_address, "ack 7"),
+                send_command(&client_address, "dance"),
+            )
+        });
+        let start = Instant::now();
+        let mut commands = vec![];
+        while commands.len() < 2 {
+            assert!(start.elapsed() < Duration::from_secs(5), "Commands were not received");
+            for request in uut.get_requests() {
+                commands.push(request.command.clone());
+                match request.command {
+                    Command::WatchSystem(_) => request.reply(Ok("Added Jita to system watch list".to_owned())),
+                    _ => request.reply(Err("No repeating alert with id 7".to_owned())),
+                }
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        let (watch, ack, dance) = client.join().unwrap();
+        assert_eq!(vec![Command::WatchSystem("Jita".to_owned()), Command::Acknowledge(Some(7))], commands);
+        assert_eq!(Ok("Added Jita to system watch list".to_owned()), watch);
+        assert_eq!(Err("No repeating alert with id 7".to_owned()), ack);
+        // Commands that do not parse are answered without the main loop
+        assert_eq!(Err("Unrecognized command: dance".to_owned()), dance);
+
+        drop(uut);
+        assert!(!address.exists());
+        assert!(send_command(&address, "ack").is_err());
+    }
+}
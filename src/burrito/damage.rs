@@ -0,0 +1,166 @@
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+
+use chrono::{DateTime, Utc};
+use regex::Regex;
+
+use super::{burrito_cfg::DamageAlert, utils};
+
+const INCOMING_DAMAGE_REGEX: &str = r"^(?<damage>[0-9]+) from (?<source>.+?)( - .*)?$";
+
+/// Rolling model of the damage each client has taken recently
+#[derive(Clone, Debug, Default)]
+pub struct DamageTracker {
+    /// Incoming hits per character as (time in ms, damage)
+    incoming: BTreeMap<String, VecDeque<(i64, u64)>>,
+    /// Characters and the alerts they are currently over the threshold of
+    active_alerts: BTreeSet<(String, DamageAlertKey)>,
+}
+
+/// Identifies a damage alert, since several may share a threshold
+type DamageAlertKey = (u32, u64, Option<String>);
+
+/// A damage alert whose threshold was crossed
+#[derive(Clone, Debug, PartialEq)]
+pub struct DamageAlertHit {
+    pub character_name: String,
+    pub alert: DamageAlert,
+    pub dps: f64,
+}
+
+impl DamageTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn record_incoming(&mut self, character_name: &str, time: DateTime<Utc>, damage: u64) {
+        self.incoming.entry(character_name.to_owned())
+            .or_default()
+            .push_back((time.timestamp_millis(), damage));
+    }
+
+    /// Average incoming DPS for a character over the last `window_ms`
+    pub fn get_dps(&self, character_name: &str, now: DateTime<Utc>, window_ms: u64) -> f64 {
+        if window_ms == 0 {
+            return 0.0;
+        }
+        let since = now.timestamp_millis() - window_ms as i64;
+        let damage: u64 = self.incoming.get(character_name)
+            .map(|hits| hits.iter().filter(|hit| hit.0 > since).map(|hit| hit.1).sum())
+            .unwrap_or(0);
+        damage as f64 * 1000.0 / window_ms as f64
+    }
+
+    /// Returns the alerts that went over their threshold since the last check
+    ///
+    /// An alert fires once when a character crosses its threshold and is re-armed
+    /// once the DPS over its window drops back below it. Alerts with a
+    /// `character_tag` only apply to characters with that tag.
+    pub fn check_alerts(&mut self, now: DateTime<Utc>, alerts: &[DamageAlert], character_tags: &BTreeMap<String, BTreeSet<String>>) -> Vec<DamageAlertHit> {
+        let mut hits = vec![];
+        let longest_window_ms = alerts.iter().map(|a| a.window_ms).max().unwrap_or(0) as i64;
+        let characters: Vec<String> = self.incoming.keys().cloned().collect();
+        for character_name in characters {
+            for alert in alerts {
+                if let Some(tag) = &alert.character_tag {
+                    let tagged = character_tags.get(&character_name)
+                        .map(|tags| tags.contains(tag))
+                        .unwrap_or(false);
+                    if !tagged {
+                        continue;
+                    }
+                }
+                let key = (character_name.to_owned(), (alert.dps, alert.window_ms, alert.character_tag.clone()));
+                let dps = self.get_dps(&character_name, now, alert.window_ms);
+                if dps >= alert.dps as f64 {
+                    if self.active_alerts.insert(key) {
+                        hits.push(DamageAlertHit { character_name: character_name.to_owned(), alert: alert.clone(), dps });
+                    }
+                }
+                else {
+                    self.active_alerts.remove(&key);
+                }
+            }
+            // Hits older than every window are no longer needed
+            if let Some(hits) = self.incoming.get_mut(&character_name) {
+                let since = now.timestamp_millis() - longest_window_ms;
+                hits.retain(|hit| hit.0 > since);
+            }
+        }
+        self.incoming.retain(|_, hits| !hits.is_empty());
+        hits
+    }
+}
+
+/// Extracts the damage and its source from the content of an incoming `(combat)` line
+pub fn parse_incoming_damage(content: &str) -> Option<(u64, String)> {
+    let regex = Regex::new(INCOMING_DAMAGE_REGEX).unwrap();
+    let content = utils::strip_tags(content);
+    let cap = regex.captures(content.trim())?;
+    Some((cap["damage"].parse().ok()?, cap["source"].trim().to_owned()))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::{BTreeMap, BTreeSet};
+
+    use chrono::{TimeZone, Utc};
+
+    use crate::burrito::burrito_cfg::DamageAlert;
+
+    use super::{parse_incoming_damage, DamageTracker};
+
+    #[test]
+    fn test_parse_incoming_damage() {
+        assert_eq!(
+            Some((523, "Dread Guristas Eliminator".to_owned())),
+            parse_incoming_damage("<color=0xffcc0000><b>523</b> <color=0x77ffffff><font size=10>from</font> <b><color=0xffffffff>Dread Guristas Eliminator</b><font size=10><color=0x77ffffff> - Hits"),
+        );
+        assert_eq!(None, parse_incoming_damage("<color=0xff00ffff><b>812</b> <color=0x77ffffff><font size=10>to</font> <b><color=0xffffffff>Dread Guristas Eliminator</b> - Smashes"));
+    }
+
+    #[test]
+    fn test_damage_alerts() {
+        let alerts = vec![DamageAlert { dps: 500, window_ms: 5000, character_tag: Some("ratting".to_owned()) }];
+        let mut tags = BTreeMap::new();
+        tags.insert("Alt A".to_owned(), BTreeSet::from(["ratting".to_owned()]));
+        let mut uut = DamageTracker::new();
+        for second in 0..5 {
+            let time = Utc.timestamp_millis_opt(second * 1000).unwrap();
+            uut.record_incoming("Alt A", time, 600);
+            uut.record_incoming("Alt B", time, 600);
+        }
+        let now = Utc.timestamp_millis_opt(4000).unwrap();
+        assert_eq!(600.0, uut.get_dps("Alt A", now, 5000));
+        // Alt B is not tagged as ratting
+        let hits = uut.check_alerts(now, &alerts, &tags);
+        assert_eq!(1, hits.len());
+        assert_eq!("Alt A", hits[0].character_name);
+        // Still over the threshold, no repeat
+        assert!(uut.check_alerts(now, &alerts, &tags).is_empty());
+        // Drops below and crosses again
+        let later = Utc.timestamp_millis_opt(20000).unwrap();
+        assert!(uut.check_alerts(later, &alerts, &tags).is_empty());
+        for ms in 0..5 {
+            uut.record_incoming("Alt A", Utc.timestamp_millis_opt(20000 + ms).unwrap(), 1000);
+        }
+        assert_eq!(1, uut.check_alerts(later, &alerts, &tags).len());
+    }
+
+    #[test]
+    fn test_damage_alerts_with_same_threshold() {
+        let burst = DamageAlert { dps: 500, window_ms: 2000, character_tag: None };
+        let sustained = DamageAlert { dps: 500, window_ms: 30000, character_tag: None };
+        let alerts = vec![burst.clone(), sustained.clone()];
+        let tags = BTreeMap::new();
+        let mut uut = DamageTracker::new();
+        uut.record_incoming("Alt A", Utc.timestamp_millis_opt(0).unwrap(), 2000);
+        let hits = uut.check_alerts(Utc.timestamp_millis_opt(1000).unwrap(), &alerts, &tags);
+        assert_eq!(vec![burst], hits.into_iter().map(|hit| hit.alert).collect::<Vec<_>>());
+        // The sustained alert fires on its own, and the burst alert that is still active does not repeat
+        for second in 1..30 {
+            uut.record_incoming("Alt A", Utc.timestamp_millis_opt(second * 1000).unwrap(), 600);
+        }
+        let hits = uut.check_alerts(Utc.timestamp_millis_opt(29500).unwrap(), &alerts, &tags);
+        assert_eq!(vec![sustained], hits.into_iter().map(|hit| hit.alert).collect::<Vec<_>>());
+    }
+}
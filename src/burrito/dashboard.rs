@@ -0,0 +1,541 @@
+use std::collections::{BTreeMap, VecDeque};
+
+use chrono::{DateTime, Utc};
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use ratatui::{prelude::*, widgets::{Block, Borders, Paragraph, Row, Table}};
+
+use super::{activity::ActivityMonitor, alert, alert_rules::AlertAction, api::ReaderInfo, commands::Command, escalation::ActiveAlert, log_watcher::{EventType, LogEvent}, notifier::Notifier};
+
+/// Name of the tab that shows the events of every channel or client
+const ALL_TAB: &str = "All";
+const MAX_MESSAGES: usize = 50;
+
+/// A feed pane whose tab the left and right keys switch
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Pane {
+    Intel,
+    Combat,
+}
+
+/// What a key press asks the main loop to do
+#[derive(Clone, Debug, PartialEq)]
+pub enum DashboardAction {
+    Quit,
+    Run(Command),
+}
+
+/// Latest report of a system near a watched system
+#[derive(Clone, Debug, PartialEq)]
+pub struct NearbySystem {
+    pub jumps: u32,
+    pub watched_system: String,
+    pub last_report: DateTime<Utc>,
+    pub reports: usize,
+}
+
+/// A character with its logs, for the clients pane
+#[derive(Clone, Debug, PartialEq)]
+pub struct ClientStatus {
+    pub character_name: String,
+    /// Chat channels being read
+    pub channels: Vec<String>,
+    pub game_log: bool,
+    pub in_space: bool,
+    /// Time of the last game log line
+    pub last_activity: Option<DateTime<Utc>>,
+}
+
+impl ClientStatus {
+    /// One status for each character that has a log being read
+    pub fn from_readers(readers: &[ReaderInfo], activity: &ActivityMonitor) -> Vec<Self> {
+        let mut clients: BTreeMap<&str, ClientStatus> = BTreeMap::new();
+        for reader in readers {
+            let client = clients.entry(&reader.character_name).or_insert_with(|| ClientStatus {
+                character_name: reader.character_name.to_owned(),
+                channels: vec![],
+                game_log: false,
+                in_space: activity.is_in_space(&reader.character_name),
+                last_activity: activity.get_last_activity(&reader.character_name),
+            });
+            match reader.chatlog {
+                true => client.channels.push(reader.channel_name.to_owned()),
+                false => client.game_log = true,
+            }
+        }
+        clients.into_values()
+            .map(|mut client| {
+                client.channels.sort();
+                client
+            })
+            .collect()
+    }
+}
+
+/// What the dashboard shows of the running instance besides its events
+pub struct DashboardState<'a> {
+    pub active_alerts: Vec<&'a ActiveAlert>,
+    pub clients: Vec<ClientStatus>,
+    pub watched_systems: Vec<String>,
+    pub watched_characters: Vec<String>,
+}
+
+/// Events split into one tab per channel or client, plus a tab with all of them
+#[derive(Clone, Debug, Default)]
+struct Feed {
+    tabs: BTreeMap<String, VecDeque<(LogEvent, bool)>>,
+    all: VecDeque<(LogEvent, bool)>,
+    /// Index into `get_tab_names()`
+    selected: usize,
+}
+
+impl Feed {
+    fn push(&mut self, tab: &str, event: &LogEvent, alert: bool, max_events: usize) {
+        let events = self.tabs.entry(tab.to_owned()).or_default();
+        for events in [events, &mut self.all] {
+            events.push_back((event.clone(), alert));
+            while events.len() > max_events {
+                events.pop_front();
+            }
+        }
+    }
+
+    fn get_tab_names(&self) -> Vec<&str> {
+        std::iter::once(ALL_TAB).chain(self.tabs.keys().map(|tab| tab.as_str())).collect()
+    }
+
+    fn get_selected_events(&self) -> &VecDeque<(LogEvent, bool)> {
+        match self.selected {
+            0 => &self.all,
+            i => self.tabs.values().nth(i - 1).unwrap_or(&self.all),
+        }
+    }
+
+    fn select_next(&mut self, forward: bool) {
+        let count = self.tabs.len() + 1;
+        self.selected = match forward {
+            true => (self.selected + 1) % count,
+            false => (self.selected + count - 1) % count,
+        };
+    }
+}
+
+/// Interactive view of `burrito tui`
+///
+/// The running instance feeds every event into the dashboard and draws it
+/// after each update. Chat events go to the intel feed, with a tab for each
+/// channel, and everything else to the spawn and combat feed, with a tab for
+/// each client.
+pub struct Dashboard {
+    max_events: usize,
+    intel: Feed,
+    combat: Feed,
+    nearby: BTreeMap<String, NearbySystem>,
+    /// Events that alerted, newest last
+    recent_alerts: VecDeque<LogEvent>,
+    messages: VecDeque<String>,
+    focus: Pane,
+    selected_alert: usize,
+    /// Command being typed after `:`
+    prompt: Option<String>,
+}
+
+impl Dashboard {
+    /// Dashboard that keeps the last `max_events` events of each feed tab
+    pub fn new(max_events: usize) -> Self {
+        Self {
+            max_events,
+            intel: Feed::default(),
+            combat: Feed::default(),
+            nearby: BTreeMap::new(),
+            recent_alerts: VecDeque::new(),
+            messages: VecDeque::new(),
+            focus: Pane::Intel,
+            selected_alert: 0,
+            prompt: None,
+        }
+    }
+
+    pub fn add_event(&mut self, event: &LogEvent, alert: bool) {
+        if event.details.channel.is_empty() {
+            self.combat.push(&event.character_name, event, alert, self.max_events);
+        }
+        else {
+            self.intel.push(&event.details.channel, event, alert, self.max_events);
+        }
+        if let EventType::RangeOfSystem(jumps) = event.event_type {
+            if !event.details.reported_system.is_empty() {
+                let reports = self.nearby.get(&event.details.reported_system).map(|system| system.reports).unwrap_or(0);
+                self.nearby.insert(event.details.reported_system.to_owned(), NearbySystem {
+                    jumps,
+                    watched_system: event.details.watched_system.to_owned(),
+                    last_report: event.time,
+                    reports: reports + 1,
+                });
+            }
+        }
+        if alert {
+            self.recent_alerts.push_back(event.clone());
+            while self.recent_alerts.len() > self.max_events {
+                self.recent_alerts.pop_front();
+            }
+        }
+    }
+
+    /// Shows a message about what Burrito is doing, such as the result of a command
+    pub fn add_message(&mut self, message: &str) {
+        for line in message.lines() {
+            self.messages.push_back(line.to_owned());
+        }
+        while self.messages.len() > MAX_MESSAGES {
+            self.messages.pop_front();
+        }
+    }
+
+    /// Reported systems, most recently reported first
+    pub fn get_nearby_systems(&self) -> Vec<(&String, &NearbySystem)> {
+        let mut systems: Vec<(&String, &NearbySystem)> = self.nearby.iter().collect();
+        systems.sort_by(|a, b| b.1.last_report.cmp(&a.1.last_report).then(a.1.jumps.cmp(&b.1.jumps)));
+        systems
+    }
+
+    pub fn get_focus(&self) -> Pane {
+        self.focus
+    }
+
+    /// Handles a key press, `alert_ids` are the ids of the active alerts in the order shown
+    ///
+    /// Tab switches between the intel and combat feeds, left and right switch
+    /// the tab of that feed, up and down select an alert, Enter acknowledges it and
+    /// `a` acknowledges all of them. `:` starts typing a command like in the
+    /// terminal, e.g. `:mute spawn 30m`.
+    pub fn handle_key(&mut self, key: KeyEvent, alert_ids: &[u32]) -> Option<DashboardAction> {
+        if let Some(prompt) = &mut self.prompt {
+            match key.code {
+                KeyCode::Enter => {
+                    let line = self.prompt.take().unwrap_or_default();
+                    return match Command::parse(&line) {
+                        Ok(command) => Some(DashboardAction::Run(command)),
+                        Err(e) => {
+                            self.add_message(&e);
+                            None
+                        },
+                    };
+                },
+                KeyCode::Esc => self.prompt = None,
+                KeyCode::Backspace => _ = prompt.pop(),
+                KeyCode::Char(c) => prompt.push(c),
+                _ => {},
+            }
+            return None;
+        }
+        self.selected_alert = self.selected_alert.min(alert_ids.len().saturating_sub(1));
+        match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => return Some(DashboardAction::Quit),
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => return Some(DashboardAction::Quit),
+            KeyCode::Char(':') => self.prompt = Some(String::new()),
+            KeyCode::Char('a') => return Some(DashboardAction::Run(Command::Acknowledge(None))),
+            KeyCode::Tab => {
+                self.focus = match self.focus {
+                    Pane::Intel => Pane::Combat,
+                    Pane::Combat => Pane::Intel,
+                };
+            },
+            KeyCode::Left | KeyCode::Right => {
+                let forward = key.code == KeyCode::Right;
+                match self.focus {
+                    Pane::Intel => self.intel.select_next(forward),
+                    Pane::Combat => self.combat.select_next(forward),
+                }
+            },
+            KeyCode::Up => self.selected_alert = self.selected_alert.saturating_sub(1),
+            KeyCode::Down => self.selected_alert = (self.selected_alert + 1).min(alert_ids.len().saturating_sub(1)),
+            KeyCode::Enter => {
+                if let Some(id) = alert_ids.get(self.selected_alert) {
+                    return Some(DashboardAction::Run(Command::Acknowledge(Some(*id))));
+                }
+            },
+            _ => {},
+        }
+        None
+    }
+
+    pub fn draw(&self, frame: &mut Frame, state: &DashboardState, now: DateTime<Utc>) {
+        let rows = Layout::new(Direction::Vertical, [
+            Constraint::Percentage(45),
+            Constraint::Percentage(30),
+            Constraint::Min(5),
+            Constraint::Length(1),
+        ]).split(frame.size());
+        let top = Layout::new(Direction::Horizontal, [Constraint::Percentage(60), Constraint::Percentage(40)]).split(rows[0]);
+        let middle = Layout::new(Direction::Horizontal, [Constraint::Percentage(60), Constraint::Percentage(40)]).split(rows[1]);
+        let bottom = Layout::new(Direction::Horizontal, [Constraint::Percentage(60), Constraint::Percentage(40)]).split(rows[2]);
+        self.draw_feed(frame, top[0], "Intel", &self.intel, Pane::Intel, |event| event.trigger.to_owned());
+        self.draw_nearby(frame, top[1], now);
+        self.draw_feed(frame, middle[0], "Spawns & combat", &self.combat, Pane::Combat, |event| {
+            format!("{} {}", event.time.format("%H:%M:%S"), alert::format_event(event))
+        });
+        self.draw_alerts(frame, middle[1], &state.active_alerts);
+        self.draw_clients(frame, bottom[0], state, now);
+        let messages: Vec<Line> = self.messages.iter().rev().take(bottom[1].height as usize).rev()
+            .map(|message| Line::from(message.as_str()))
+            .collect();
+        frame.render_widget(Paragraph::new(messages).block(Block::new().borders(Borders::ALL).title(" Messages ")), bottom[1]);
+        let status = match &self.prompt {
+            Some(prompt) => Line::from(format!(":{prompt}")),
+            None => Line::styled("q quit  Tab switch feed  ←/→ switch tab  ↑/↓ select alert  Enter acknowledge  a acknowledge all  : command", Style::new().dim()),
+        };
+        frame.render_widget(Paragraph::new(status), rows[3]);
+    }
+
+    /// Bordered block, highlighted if it is the feed the arrow keys switch
+    fn get_block<'a>(&self, title: Line<'a>, pane: Option<Pane>) -> Block<'a> {
+        let block = Block::new().borders(Borders::ALL).title(title);
+        match pane == Some(self.focus) {
+            true => block.border_style(Style::new().fg(Color::Cyan)),
+            false => block,
+        }
+    }
+
+    fn draw_feed(&self, frame: &mut Frame, area: Rect, title: &str, feed: &Feed, pane: Pane, format: impl Fn(&LogEvent) -> String) {
+        let mut spans = vec![Span::raw(format!(" {title}: "))];
+        for (i, name) in feed.get_tab_names().into_iter().enumerate() {
+            if i > 0 {
+                spans.push(Span::raw(" | "));
+            }
+            match i == feed.selected {
+                true => spans.push(Span::raw(name.to_owned()).reversed()),
+                false => spans.push(Span::raw(name.to_owned())),
+            }
+        }
+        spans.push(Span::raw(" "));
+        let height = area.height.saturating_sub(2) as usize;
+        let lines: Vec<Line> = feed.get_selected_events().iter().rev().take(height).rev()
+            .map(|(event, alert)| Line::styled(format(event), get_style(event, *alert)))
+            .collect();
+        frame.render_widget(Paragraph::new(lines).block(self.get_block(Line::from(spans), Some(pane))), area);
+    }
+
+    fn draw_nearby(&self, frame: &mut Frame, area: Rect, now: DateTime<Utc>) {
+        let rows: Vec<Row> = self.get_nearby_systems().into_iter()
+            .map(|(name, system)| {
+                let style = get_distance_style(system.jumps);
+                Row::new(vec![
+                    name.to_owned(),
+                    system.jumps.to_string(),
+                    system.watched_system.to_owned(),
+                    format_age(now, system.last_report),
+                    system.reports.to_string(),
+                ]).style(style)
+            })
+            .collect();
+        let table = Table::new(rows, [
+            Constraint::Length(10),
+            Constraint::Length(5),
+            Constraint::Length(10),
+            Constraint::Length(9),
+            Constraint::Length(7),
+        ])
+            .header(Row::new(vec!["System", "Jumps", "From", "Reported", "Reports"]).style(Style::new().bold()))
+            .block(self.get_block(Line::from(" Nearby systems "), None));
+        frame.render_widget(table, area);
+    }
+
+    fn draw_alerts(&self, frame: &mut Frame, area: Rect, active_alerts: &[&ActiveAlert]) {
+        let mut lines: Vec<Line> = active_alerts.iter().enumerate()
+            .map(|(i, active_alert)| {
+                let text = format!("#{} x{} {}", active_alert.id, active_alert.repeats + 1, alert::format_event(&active_alert.event));
+                let style = get_style(&active_alert.event, true);
+                match i == self.selected_alert.min(active_alerts.len().saturating_sub(1)) {
+                    true => Line::styled(text, style.reversed()),
+                    false => Line::styled(text, style),
+                }
+            })
+            .collect();
+        let height = area.height.saturating_sub(2) as usize;
+        let recent = height.saturating_sub(lines.len());
+        // Alerts without a repeat policy need no acknowledging, they are listed below for reference
+        lines.extend(self.recent_alerts.iter().rev().take(recent)
+            .map(|event| Line::styled(format!("{} {}", event.time.format("%H:%M:%S"), alert::format_event(event)), Style::new().dim())));
+        let title = format!(" Alerts ({} active) ", active_alerts.len());
+        frame.render_widget(Paragraph::new(lines).block(self.get_block(Line::from(title), None)), area);
+    }
+
+    fn draw_clients(&self, frame: &mut Frame, area: Rect, state: &DashboardState, now: DateTime<Utc>) {
+        let mut lines = vec![
+            Line::from(format!("Watching systems: {}", state.watched_systems.join(", "))),
+            Line::from(format!("Watching characters: {}", state.watched_characters.join(", "))),
+        ];
+        if state.clients.is_empty() {
+            lines.push(Line::styled("No logs are being read", Style::new().dim()));
+        }
+        for client in &state.clients {
+            let mut logs = client.channels.clone();
+            if client.game_log {
+                logs.push("game log".to_owned());
+            }
+            let activity = match (client.game_log, client.last_activity) {
+                (true, Some(time)) => format!("{}, last game log line {}", if client.in_space { "in space" } else { "docked" }, format_age(now, time)),
+                (true, None) => "no game log lines yet".to_owned(),
+                (false, _) => "no game log".to_owned(),
+            };
+            lines.push(Line::from(vec![
+                Span::raw(client.character_name.to_owned()).bold(),
+                Span::raw(format!(" {activity}: {}", logs.join(", "))),
+            ]));
+        }
+        frame.render_widget(Paragraph::new(lines).block(self.get_block(Line::from(" Clients "), None)), area);
+    }
+}
+
+/// Replaces the terminal notifier in `burrito tui`, which shows alerts in the alerts pane
+pub struct DashboardNotifier;
+
+impl Notifier for DashboardNotifier {
+    fn notify(&mut self, _event: &LogEvent, _action: &AlertAction) -> Result<(), String> {
+        Ok(())
+    }
+}
+
+fn get_style(event: &LogEvent, alert: bool) -> Style {
+    if alert {
+        return Style::new().fg(Color::Red).bold();
+    }
+    match event.event_type {
+        EventType::RangeOfSystem(jumps) => get_distance_style(jumps),
+        EventType::SystemClear(_) => Style::new().fg(Color::Green),
+        EventType::FactionSpawn | EventType::DreadSpawn | EventType::TitanSpawn | EventType::OfficerSpawn => Style::new().fg(Color::Magenta),
+        EventType::ChatConnectionLost | EventType::ClientInactive | EventType::DamageThreshold(_) => Style::new().fg(Color::Yellow),
+        _ => Style::new(),
+    }
+}
+
+fn get_distance_style(jumps: u32) -> Style {
+    match jumps {
+        0..=2 => Style::new().fg(Color::Red),
+        3..=5 => Style::new().fg(Color::Yellow),
+        _ => Style::new(),
+    }
+}
+
+/// How long ago something happened, e.g. `45s ago` or `3m ago`
+fn format_age(now: DateTime<Utc>, time: DateTime<Utc>) -> String {
+    let secs = (now - time).num_seconds().max(0);
+    match secs {
+        0..=59 => format!("{secs}s ago"),
+        60..=3599 => format!("{}m ago", secs / 60),
+        _ => format!("{}h ago", secs / 3600),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, TimeZone, Utc};
+    use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+    use ratatui::{backend::TestBackend, Terminal};
+
+    use crate::burrito::{activity::ActivityMonitor, api::ReaderInfo, commands::Command, log_watcher::{EventType, LogEvent}};
+
+    use super::{ClientStatus, Dashboard, DashboardAction, DashboardState, Pane};
+
+    fn event(seconds: i64, character_name: &str, event_type: EventType, channel: &str, system: &str) -> LogEvent {
+        LogEvent::test(event_type)
+            .with_time(Utc.with_ymd_and_hms(2023, 9, 18, 12, 0, 0).unwrap() + Duration::seconds(seconds))
+            .with_character(character_name)
+            .with_trigger(format!("[ 2023.09.18 12:00:00 ] Reporter > {system} +1"))
+            .with_message("Dark Blood Arch Templar")
+            .with_channel(channel)
+            .with_system(system)
+            .with_watched_system("1DQ1-A")
+    }
+
+    fn key(code: KeyCode) -> KeyEvent {
+        KeyEvent::new(code, KeyModifiers::NONE)
+    }
+
+    #[test]
+    fn test_dashboard_feeds() {
+        let mut uut = Dashboard::new(2);
+        uut.add_event(&event(0, "Alt A", EventType::RangeOfSystem(3), "delve.imperium", "UALX-3"), true);
+        uut.add_event(&event(10, "Alt A", EventType::RangeOfSystem(5), "querious.imperium", "Y-2ANO"), false);
+        uut.add_event(&event(20, "Alt B", EventType::RangeOfSystem(2), "delve.imperium", "UALX-3"), false);
+        uut.add_event(&event(30, "Alt B", EventType::FactionSpawn, "", ""), true);
+
+        assert_eq!(vec!["All", "delve.imperium", "querious.imperium"], uut.intel.get_tab_names());
+        // Only the last two events are kept in each tab
+        assert_eq!(2, uut.intel.get_selected_events().len());
+        assert_eq!(vec!["All", "Alt B"], uut.combat.get_tab_names());
+        let nearby = uut.get_nearby_systems();
+        assert_eq!(vec!["UALX-3", "Y-2ANO"], nearby.iter().map(|(name, _)| name.as_str()).collect::<Vec<&str>>());
+        assert_eq!((2, 2), (nearby[0].1.jumps, nearby[0].1.reports));
+        assert_eq!(2, uut.recent_alerts.len());
+
+        uut.handle_key(key(KeyCode::Left), &[]);
+        assert_eq!(2, uut.intel.selected);
+        uut.handle_key(key(KeyCode::Tab), &[]);
+        assert_eq!(Pane::Combat, uut.get_focus());
+        uut.handle_key(key(KeyCode::Right), &[]);
+        assert_eq!(1, uut.combat.selected);
+        assert_eq!(2, uut.intel.selected);
+    }
+
+    #[test]
+    fn test_dashboard_keys() {
+        let mut uut = Dashboard::new(10);
+        assert_eq!(Some(DashboardAction::Run(Command::Acknowledge(Some(4)))), uut.handle_key(key(KeyCode::Enter), &[4, 7]));
+        uut.handle_key(key(KeyCode::Down), &[4, 7]);
+        uut.handle_key(key(KeyCode::Down), &[4, 7]);
+        assert_eq!(Some(DashboardAction::Run(Command::Acknowledge(Some(7)))), uut.handle_key(key(KeyCode::Enter), &[4, 7]));
+        // The selection follows alerts that were acknowledged elsewhere
+        assert_eq!(None, uut.handle_key(key(KeyCode::Enter), &[]));
+        assert_eq!(Some(DashboardAction::Run(Command::Acknowledge(None))), uut.handle_key(key(KeyCode::Char('a')), &[]));
+
+        uut.handle_key(key(KeyCode::Char(':')), &[]);
+        for c in "mute spawn 30mm".chars() {
+            uut.handle_key(key(KeyCode::Char(c)), &[]);
+        }
+        uut.handle_key(key(KeyCode::Backspace), &[]);
+        // Keys go to the prompt while typing a command
+        assert_eq!(None, uut.handle_key(key(KeyCode::Char('q')), &[]));
+        uut.handle_key(key(KeyCode::Backspace), &[]);
+        assert_eq!(
+            Some(DashboardAction::Run(Command::Mute(vec!["spawn".to_owned(), "30m".to_owned()]))),
+            uut.handle_key(key(KeyCode::Enter), &[]),
+        );
+        uut.handle_key(key(KeyCode::Char(':')), &[]);
+        uut.handle_key(key(KeyCode::Char('x')), &[]);
+        assert_eq!(None, uut.handle_key(key(KeyCode::Enter), &[]));
+        assert_eq!(Some(&"Unrecognized command: x".to_owned()), uut.messages.back());
+        assert_eq!(Some(DashboardAction::Quit), uut.handle_key(key(KeyCode::Char('q')), &[]));
+        assert_eq!(Some(DashboardAction::Quit), uut.handle_key(KeyEvent::new(KeyCode::Char('c'), KeyModifiers::CONTROL), &[]));
+    }
+
+    #[test]
+    fn test_dashboard_draw() {
+        let mut uut = Dashboard::new(10);
+        let spawn = event(30, "Alt B", EventType::FactionSpawn, "", "");
+        uut.add_event(&event(0, "Alt A", EventType::RangeOfSystem(3), "delve.imperium", "UALX-3"), false);
+        uut.add_event(&spawn, true);
+        uut.add_message("Reloaded burrito.cfg");
+        let readers = vec![
+            ReaderInfo { character_name: "Alt A".to_owned(), channel_name: "delve.imperium".to_owned(), log_file: "a.txt".to_owned(), chatlog: true },
+            ReaderInfo { character_name: "Alt A".to_owned(), channel_name: "Local".to_owned(), log_file: "b.txt".to_owned(), chatlog: true },
+            ReaderInfo { character_name: "Alt B".to_owned(), channel_name: String::new(), log_file: "c.txt".to_owned(), chatlog: false },
+        ];
+        let clients = ClientStatus::from_readers(&readers, &ActivityMonitor::new());
+        assert_eq!(vec!["Local".to_owned(), "delve.imperium".to_owned()], clients[0].channels);
+        assert!(!clients[0].game_log && clients[1].game_log);
+        let state = DashboardState {
+            active_alerts: vec![],
+            clients,
+            watched_systems: vec!["1DQ1-A".to_owned()],
+            watched_characters: vec![],
+        };
+
+        let mut terminal = Terminal::new(TestBackend::new(120, 40)).unwrap();
+        terminal.draw(|frame| uut.draw(frame, &state, spawn.time)).unwrap();
+        let screen: String = terminal.backend().buffer().content().iter().map(|cell| cell.symbol()).collect();
+        for text in ["Intel: All | delve.imperium", "UALX-3", "30s ago", "[Alt B]", "Watching systems: 1DQ1-A", "Reloaded burrito.cfg"] {
+            assert!(screen.contains(text), "{text} is not shown");
+        }
+    }
+}
@@ -0,0 +1,50 @@
+use std::sync::{mpsc::{self, Receiver, Sender}, Mutex};
+
+/// Where `report` sends its messages while they are captured
+static CAPTURE: Mutex<Option<Sender<String>>> = Mutex::new(None);
+
+/// Reports an error from code that cannot return it, such as a background thread
+///
+/// Messages are printed to stderr unless `capture` was called, e.g. because the
+/// dashboard owns the terminal.
+pub fn report(message: impl Into<String>) {
+    let message = message.into();
+    let capture = CAPTURE.lock().unwrap_or_else(|e| e.into_inner());
+    let message = match capture.as_ref() {
+        Some(sender) => match sender.send(message) {
+            Ok(()) => return,
+            Err(e) => e.0,
+        },
+        None => message,
+    };
+    eprintln!("{message}");
+}
+
+/// Sends the messages of `report` to the returned receiver instead of stderr
+/// until `release` is called
+pub fn capture() -> Receiver<String> {
+    let (sender, receiver) = mpsc::channel();
+    *CAPTURE.lock().unwrap_or_else(|e| e.into_inner()) = Some(sender);
+    receiver
+}
+
+/// Prints the messages of `report` to stderr again
+pub fn release() {
+    *CAPTURE.lock().unwrap_or_else(|e| e.into_inner()) = None;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{capture, release, report};
+
+    #[test]
+    fn test_capture() {
+        let receiver = capture();
+        report("Unable to send webhook");
+        // Other tests may report at the same time
+        assert!(receiver.try_iter().any(|message| message == "Unable to send webhook"));
+        release();
+        report("Printed to stderr");
+        assert!(!receiver.try_iter().any(|message| message == "Printed to stderr"));
+    }
+}
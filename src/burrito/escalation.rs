@@ -0,0 +1,185 @@
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, Utc};
+
+use super::{alert_rules::{AlertAction, EscalationPolicy}, log_watcher::LogEvent};
+
+/// Shortest time between repeats, used for policies with a smaller `repeat_interval_ms`
+const MIN_REPEAT_INTERVAL_MS: u64 = 1000;
+
+/// An alert that repeats until it is acknowledged
+#[derive(Clone, Debug)]
+pub struct ActiveAlert {
+    pub id: u32,
+    pub event: LogEvent,
+    actions: Vec<AlertAction>,
+    policy: EscalationPolicy,
+    last_fired: DateTime<Utc>,
+    /// How often the alert has repeated so far
+    pub repeats: u32,
+}
+
+impl ActiveAlert {
+    /// Actions for the next repeat, after escalation if it is due
+    fn get_repeat_actions(&self) -> Vec<AlertAction> {
+        let policy = &self.policy;
+        if policy.escalate_after > 0 && self.repeats >= policy.escalate_after && !policy.escalation_actions.is_empty() {
+            policy.escalation_actions.clone()
+        }
+        else {
+            self.actions.clone()
+        }
+    }
+}
+
+/// Alerts whose rules have an escalation policy and that were not acknowledged yet
+#[derive(Clone, Debug)]
+pub struct ActiveAlerts {
+    alerts: BTreeMap<u32, ActiveAlert>,
+    next_id: u32,
+}
+
+impl Default for ActiveAlerts {
+    fn default() -> Self {
+        Self {
+            alerts: BTreeMap::new(),
+            next_id: 1,
+        }
+    }
+}
+
+impl ActiveAlerts {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Starts tracking an alert that just fired, returns its id
+    pub fn add(&mut self, event: LogEvent, actions: Vec<AlertAction>, mut policy: EscalationPolicy, now: DateTime<Utc>) -> u32 {
+        policy.repeat_interval_ms = policy.repeat_interval_ms.max(MIN_REPEAT_INTERVAL_MS);
+        let id = self.next_id;
+        self.next_id += 1;
+        self.alerts.insert(id, ActiveAlert { id, event, actions, policy, last_fired: now, repeats: 0 });
+        id
+    }
+
+    /// Returns the alerts that are due to repeat with the actions to run
+    ///
+    /// Alerts that reached their `max_repeats` are dropped after their last repeat.
+    /// Repeats of alerts for which `is_muted` returns true are skipped and not counted.
+    pub fn get_due(&mut self, now: DateTime<Utc>, is_muted: impl Fn(&LogEvent) -> bool) -> Vec<(ActiveAlert, Vec<AlertAction>)> {
+        let mut due = vec![];
+        for alert in self.alerts.values_mut() {
+            if (now - alert.last_fired).num_milliseconds() < alert.policy.repeat_interval_ms as i64 {
+                continue;
+            }
+            if is_muted(&alert.event) {
+                alert.last_fired = now;
+                continue;
+            }
+            let actions = alert.get_repeat_actions();
+            alert.repeats += 1;
+            alert.last_fired = now;
+            due.push((alert.clone(), actions));
+        }
+        self.alerts.retain(|_, alert| alert.policy.max_repeats == 0 || alert.repeats < alert.policy.max_repeats);
+        due
+    }
+
+    /// Stops one alert from repeating, or every alert if `id` is `None`
+    ///
+    /// Returns the number of alerts that were acknowledged.
+    pub fn acknowledge(&mut self, id: Option<u32>) -> usize {
+        match id {
+            Some(id) => self.alerts.remove(&id).map(|_| 1).unwrap_or(0),
+            None => {
+                let count = self.alerts.len();
+                self.alerts.clear();
+                count
+            },
+        }
+    }
+
+    pub fn get_active(&self) -> Vec<&ActiveAlert> {
+        self.alerts.values().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{TimeZone, Utc};
+
+    use crate::burrito::{alert_rules::{AlertAction, EscalationPolicy}, log_watcher::{EventType, LogEvent}};
+
+    use super::ActiveAlerts;
+
+    fn event() -> LogEvent {
+        LogEvent::test(EventType::RangeOfSystem(1))
+            .with_trigger("Bad Guy Jita")
+            .with_message("Bad Guy is 1 jump away")
+    }
+
+    #[test]
+    fn test_escalation() {
+        let event = event();
+        let policy = EscalationPolicy {
+            repeat_interval_ms: 10000,
+            escalate_after: 2,
+            escalation_actions: vec![AlertAction::sound("loud.mp3")],
+            max_repeats: 0,
+        };
+        let at = |secs: i64| Utc.timestamp_millis_opt(secs * 1000).unwrap();
+        let mut uut = ActiveAlerts::new();
+        let first = uut.add(event.clone(), vec![AlertAction::sound("quiet.mp3")], policy.clone(), at(0));
+        let second = uut.add(event, vec![AlertAction::Print], policy, at(5));
+
+        assert!(uut.get_due(at(5), |_| false).is_empty());
+        let due = uut.get_due(at(10), |_| false);
+        assert_eq!(1, due.len());
+        assert_eq!(first, due[0].0.id);
+        assert_eq!(vec![AlertAction::sound("quiet.mp3")], due[0].1);
+        assert_eq!(2, uut.get_due(at(20), |_| false).len());
+        // Escalates after the second repeat
+        let due = uut.get_due(at(30), |_| false);
+        assert_eq!(vec![AlertAction::sound("loud.mp3")], due[0].1);
+
+        assert_eq!(1, uut.acknowledge(Some(first)));
+        assert_eq!(0, uut.acknowledge(Some(first)));
+        assert_eq!(vec![second], uut.get_active().iter().map(|a| a.id).collect::<Vec<u32>>());
+        assert_eq!(1, uut.acknowledge(None));
+        assert!(uut.get_due(at(100), |_| false).is_empty());
+    }
+
+    #[test]
+    fn test_zero_repeat_interval() {
+        let policy = EscalationPolicy {
+            repeat_interval_ms: 0,
+            escalate_after: 0,
+            escalation_actions: vec![],
+            max_repeats: 0,
+        };
+        assert!(policy.validate().is_err());
+        let at = |millis: i64| Utc.timestamp_millis_opt(millis).unwrap();
+        let mut uut = ActiveAlerts::default();
+        assert_eq!(1, uut.add(event(), vec![AlertAction::Print], policy, at(0)));
+        assert!(uut.get_due(at(500), |_| false).is_empty());
+        assert_eq!(1, uut.get_due(at(1000), |_| false).len());
+        assert!(uut.get_due(at(1500), |_| false).is_empty());
+    }
+
+    #[test]
+    fn test_muted_repeats() {
+        let policy = EscalationPolicy {
+            repeat_interval_ms: 10000,
+            escalate_after: 0,
+            escalation_actions: vec![],
+            max_repeats: 1,
+        };
+        let at = |secs: i64| Utc.timestamp_millis_opt(secs * 1000).unwrap();
+        let mut uut = ActiveAlerts::new();
+        uut.add(event(), vec![AlertAction::Print], policy, at(0));
+        assert!(uut.get_due(at(10), |_| true).is_empty());
+        assert!(uut.get_due(at(15), |_| false).is_empty());
+        assert_eq!(1, uut.get_due(at(20), |_| false).len());
+        assert!(uut.get_active().is_empty());
+    }
+}
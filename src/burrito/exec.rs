@@ -0,0 +1,148 @@
+use std::{collections::HashMap, io::Write, process::{Child, Command, Stdio}, sync::{atomic::{AtomicUsize, Ordering}, Arc}, thread, time::{Duration, Instant}};
+
+use super::{alert, alert_rules::AlertAction, diagnostics, log_watcher::LogEvent, notifier::Notifier};
+
+const POLL_INTERVAL_MS: u64 = 50;
+
+/// Runs external programs for alerts
+///
+/// The event is passed to the program both as `BURRITO_<FIELD>` environment
+/// variables and as a JSON object on stdin, using the fields from
+/// `alert::get_event_fields`. Programs are killed once they run longer than the
+/// action's timeout, and an action is skipped while `max_running` copies of
+/// its program are still running.
+#[derive(Default)]
+pub struct ExecNotifier {
+    running: HashMap<AlertAction, Arc<AtomicUsize>>,
+}
+
+impl ExecNotifier {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Number of programs started by an action that are still running
+    pub fn get_running(&self, action: &AlertAction) -> usize {
+        self.running.get(action).map(|r| r.load(Ordering::SeqCst)).unwrap_or(0)
+    }
+}
+
+impl Notifier for ExecNotifier {
+    fn notify(&mut self, event: &LogEvent, action: &AlertAction) -> Result<(), String> {
+        if let AlertAction::Exec { command, args, timeout_ms, max_running } = action {
+            let running = self.running.entry(action.clone()).or_default().clone();
+            if running.load(Ordering::SeqCst) >= *max_running {
+                return Err(format!("{command} is already running {max_running} time(s), skipping"));
+            }
+            let fields = alert::get_event_fields(event);
+            let mut child = Command::new(command)
+                .args(args)
+                .envs(fields.iter().map(|(name, value)| (format!("BURRITO_{}", name.to_uppercase()), value)))
+                .stdin(Stdio::piped())
+                .stdout(Stdio::null())
+                .spawn()
+                .map_err(|e| format!("Unable to run {command}: {e}"))?;
+            running.fetch_add(1, Ordering::SeqCst);
+            let input = alert::get_event_json(event).to_string();
+            let command = command.to_owned();
+            let timeout = Duration::from_millis(*timeout_ms);
+            thread::spawn(move || {
+                if let Some(mut stdin) = child.stdin.take() {
+                    // Programs that do not read stdin close it early, that is fine
+                    _ = writeln!(stdin, "{input}");
+                }
+                wait_or_kill(&mut child, &command, timeout);
+                running.fetch_sub(1, Ordering::SeqCst);
+            });
+        }
+        Ok(())
+    }
+}
+
+fn wait_or_kill(child: &mut Child, command: &str, timeout: Duration) {
+    let start = Instant::now();
+    loop {
+        match child.try_wait() {
+            Ok(Some(status)) => {
+                if !status.success() {
+                    diagnostics::report(format!("{command} exited with {status}"));
+                }
+                return;
+            },
+            Ok(None) => {},
+            Err(e) => {
+                diagnostics::report(format!("Unable to wait for {command}: {e}"));
+                return;
+            },
+        }
+        if start.elapsed() >= timeout {
+            diagnostics::report(format!("{command} timed out after {}ms, killing it", timeout.as_millis()));
+            _ = child.kill();
+            _ = child.wait();
+            return;
+        }
+        thread::sleep(Duration::from_millis(POLL_INTERVAL_MS));
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use std::{thread, time::{Duration, Instant}};
+
+    use crate::burrito::{alert_rules::AlertAction, log_watcher::{EventType, LogEvent}, notifier::Notifier, utils::TestDir};
+
+    use super::ExecNotifier;
+
+    fn event() -> LogEvent {
+        LogEvent::test(EventType::RangeOfSystem(3))
+            .with_trigger("Bad Guy Jita")
+            .with_message("Bad Guy is 3 jumps away")
+            .with_system("Jita")
+    }
+
+    fn wait_until_done(uut: &ExecNotifier, action: &AlertAction) {
+        let start = Instant::now();
+        while uut.get_running(action) > 0 {
+            assert!(start.elapsed() < Duration::from_secs(5));
+            thread::sleep(Duration::from_millis(10));
+        }
+    }
+
+    #[test]
+    fn test_exec_event_details() {
+        let dir = TestDir::new("exec");
+        let file = dir.get_path("output.txt");
+        let action = AlertAction::Exec {
+            command: "sh".to_owned(),
+            args: vec!["-c".to_owned(), format!("cat > {file}; echo \"$BURRITO_CHARACTER_NAME $BURRITO_DISTANCE $BURRITO_SYSTEM\" >> {file}")],
+            timeout_ms: 5000,
+            max_running: 1,
+        };
+        let mut uut = ExecNotifier::new();
+        assert_eq!(Ok(()), uut.notify(&event(), &action));
+        wait_until_done(&uut, &action);
+        let output = std::fs::read_to_string(&file).unwrap();
+        let lines: Vec<&str> = output.lines().collect();
+        let json: serde_json::Value = serde_json::from_str(lines[0]).unwrap();
+        assert_eq!("RangeOfSystem", json["event_type"]);
+        assert_eq!(3, json["distance"]);
+        assert_eq!("Bad Guy Jita", json["trigger"]);
+        assert_eq!("Alt A 3 Jita", lines[1]);
+    }
+
+    #[test]
+    fn test_exec_timeout_and_limit() {
+        let action = AlertAction::Exec {
+            command: "sleep".to_owned(),
+            args: vec!["10".to_owned()],
+            timeout_ms: 200,
+            max_running: 1,
+        };
+        let mut uut = ExecNotifier::new();
+        assert_eq!(Ok(()), uut.notify(&event(), &action));
+        assert!(uut.notify(&event(), &action).is_err());
+        assert_eq!(1, uut.get_running(&action));
+        wait_until_done(&uut, &action);
+        assert_eq!(Ok(()), uut.notify(&event(), &action));
+    }
+}
@@ -0,0 +1,232 @@
+use std::{fs::{self, File, OpenOptions}, io::{BufRead, BufReader, Write}, path::{Path, PathBuf}};
+
+use chrono::{DateTime, Duration, NaiveDate, NaiveDateTime, Utc};
+
+use super::{diagnostics, log_watcher::{EventKind, LogEvent}, mutes, utils};
+
+const FILE_PREFIX: &str = "events-";
+const FILE_EXTENSION: &str = ".jsonl";
+
+/// Append-only record of every event, one JSON line per event and one file per day (UTC)
+pub struct Journal {
+    dir: PathBuf,
+    retention_days: u32,
+    /// Day of the open file and the file itself
+    file: Option<(NaiveDate, File)>,
+}
+
+impl Journal {
+    /// Journal in `.burrito/journal/`
+    pub fn open_default(retention_days: u32) -> Self {
+        Self::new(get_default_dir(), retention_days)
+    }
+
+    /// Journal in `dir`, which keeps files for `retention_days` days (0 keeps them forever)
+    pub fn new(dir: PathBuf, retention_days: u32) -> Self {
+        Self {
+            dir,
+            retention_days,
+            file: None,
+        }
+    }
+
+    pub fn append(&mut self, event: &LogEvent) -> Result<(), String> {
+        let day = event.time.date_naive();
+        let file = match &mut self.file {
+            Some((file_day, file)) if *file_day == day => file,
+            _ => {
+                fs::create_dir_all(&self.dir).map_err(|e| format!("Unable to create {}: {e}", self.dir.display()))?;
+                let path = self.dir.join(get_file_name(day));
+                let file = OpenOptions::new().create(true).append(true).open(&path)
+                    .map_err(|e| format!("Unable to open {}: {e}", path.display()))?;
+                self.file = Some((day, file));
+                self.prune(day);
+                &mut self.file.as_mut().unwrap().1
+            },
+        };
+        let line = serde_json::to_string(event).map_err(|e| e.to_string())?;
+        writeln!(file, "{line}").map_err(|e| format!("Unable to write to the journal: {e}"))
+    }
+
+    /// Deletes the files that are older than the retention period
+    fn prune(&self, today: NaiveDate) {
+        if self.retention_days == 0 {
+            return;
+        }
+        let oldest = today - Duration::days(self.retention_days as i64 - 1);
+        for (day, path) in get_files(&self.dir) {
+            if day < oldest {
+                if let Err(e) = fs::remove_file(&path) {
+                    diagnostics::report(format!("Unable to delete old journal file {}: {e}", path.display()));
+                }
+            }
+        }
+    }
+}
+
+pub fn get_default_dir() -> PathBuf {
+    Path::new(&utils::get_burrito_dir()).join("journal")
+}
+
+fn get_file_name(day: NaiveDate) -> String {
+    format!("{FILE_PREFIX}{}{FILE_EXTENSION}", day.format("%Y-%m-%d"))
+}
+
+/// Journal files in `dir` with their day, oldest first
+fn get_files(dir: &Path) -> Vec<(NaiveDate, PathBuf)> {
+    let mut files: Vec<(NaiveDate, PathBuf)> = match fs::read_dir(dir) {
+        Ok(entries) => entries.filter_map(|entry| {
+            let path = entry.ok()?.path();
+            let name = path.file_name()?.to_str()?;
+            let day = name.strip_prefix(FILE_PREFIX)?.strip_suffix(FILE_EXTENSION)?;
+            Some((NaiveDate::parse_from_str(day, "%Y-%m-%d").ok()?, path))
+        }).collect(),
+        Err(_) => vec![],
+    };
+    files.sort();
+    files
+}
+
+/// Filter for `burrito history`
+#[derive(Clone, Debug, PartialEq)]
+pub struct JournalQuery {
+    pub since: DateTime<Utc>,
+    pub until: Option<DateTime<Utc>>,
+    /// Event kinds to include, all if empty
+    pub kinds: Vec<EventKind>,
+    /// Reported or watched system
+    pub system: Option<String>,
+    /// Character that read the log or reported the intel
+    pub character: Option<String>,
+    pub channel: Option<String>,
+}
+
+impl JournalQuery {
+    /// Parses the arguments of `burrito history`
+    ///
+    /// Times are either a duration before `now` like `2h`, or an EVE (UTC) time like
+    /// `2023.09.18 12:00` or `2023.09.18`. Without `--since`, the last 24 hours are shown.
+    pub fn parse(args: &[String], now: DateTime<Utc>) -> Result<Self, String> {
+        let mut query = Self {
+            since: now - Duration::hours(24),
+            until: None,
+            kinds: vec![],
+            system: None,
+            character: None,
+            channel: None,
+        };
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            let value = args.next().ok_or(format!("{arg} requires a value"))?;
+            match arg.as_str() {
+                "--since" => query.since = parse_time(value, now)?,
+                "--until" => query.until = Some(parse_time(value, now)?),
+                "--type" => {
+                    for kind in value.split(',') {
+                        query.kinds.extend(mutes::get_category_kinds(kind.trim())?);
+                    }
+                },
+                "--system" => query.system = Some(value.to_owned()),
+                "--character" => query.character = Some(value.to_owned()),
+                "--channel" => query.channel = Some(value.to_owned()),
+                _ => return Err(format!("Unrecognized option: {arg}")),
+            }
+        }
+        Ok(query)
+    }
+
+    pub fn matches(&self, event: &LogEvent) -> bool {
+        let matches_text = |filter: &Option<String>, values: &[&str]| match filter {
+            Some(filter) => values.iter().any(|value| value.eq_ignore_ascii_case(filter)),
+            None => true,
+        };
+        let details = &event.details;
+        event.time >= self.since
+            && self.until.map(|until| event.time < until).unwrap_or(true)
+            && (self.kinds.is_empty() || self.kinds.contains(&event.event_type.get_kind()))
+            && matches_text(&self.system, &[&details.reported_system, &details.watched_system])
+            && matches_text(&self.character, &[&event.character_name, &details.reporter])
+            && matches_text(&self.channel, &[&details.channel])
+    }
+
+    /// Reads the events in the journal in `dir` that match, oldest first
+    pub fn run(&self, dir: &Path) -> Vec<LogEvent> {
+        let first_day = self.since.date_naive();
+        let last_day = self.until.map(|until| until.date_naive());
+        get_files(dir).into_iter()
+            .filter(|(day, _)| *day >= first_day && last_day.map(|last_day| *day <= last_day).unwrap_or(true))
+            .filter_map(|(_, path)| File::open(path).ok())
+            .flat_map(|file| BufReader::new(file).lines().map_while(Result::ok))
+            .filter_map(|line| serde_json::from_str::<LogEvent>(&line).ok())
+            .filter(|event| self.matches(event))
+            .collect()
+    }
+}
+
+fn parse_time(text: &str, now: DateTime<Utc>) -> Result<DateTime<Utc>, String> {
+    if let Some(duration) = utils::parse_duration(text) {
+        return Ok(now - duration);
+    }
+    if let Ok(time) = NaiveDateTime::parse_from_str(text, "%Y.%m.%d %H:%M") {
+        return Ok(time.and_utc());
+    }
+    if let Ok(day) = NaiveDate::parse_from_str(text, "%Y.%m.%d") {
+        return Ok(day.and_hms_opt(0, 0, 0).unwrap().and_utc());
+    }
+    Err(format!("Invalid time: {text}, expected e.g. 2h, 2023.09.18 or \"2023.09.18 12:00\""))
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, TimeZone, Utc};
+
+    use crate::burrito::{log_watcher::{EventKind, EventType, LogEvent}, utils::TestDir};
+
+    use super::{Journal, JournalQuery};
+
+    fn event(hours: i64, event_type: EventType, system: &str) -> LogEvent {
+        LogEvent::test(event_type)
+            .with_time(Utc.with_ymd_and_hms(2023, 9, 18, 12, 0, 0).unwrap() + Duration::hours(hours))
+            .with_trigger(format!("Reporter > {system} +3"))
+            .with_channel("east.imperium")
+            .with_reporter("Reporter")
+            .with_system(system)
+            .with_watched_system("1DQ1-A")
+    }
+
+    fn args(line: &str) -> Vec<String> {
+        line.split_whitespace().map(|w| w.to_owned()).collect()
+    }
+
+    #[test]
+    fn test_journal() {
+        let dir = TestDir::new("journal");
+        let mut uut = Journal::new(dir.to_path_buf(), 2);
+        let events = [
+            event(-48, EventType::RangeOfSystem(3), "UALX-3"),
+            event(-24, EventType::RangeOfSystem(2), "UALX-3"),
+            event(0, EventType::RangeOfSystem(1), "Y-2ANO"),
+            event(1, EventType::FactionSpawn, ""),
+        ];
+        for event in &events {
+            uut.append(event).unwrap();
+        }
+        // The file from two days ago is past the retention period
+        assert_eq!(2, std::fs::read_dir(&dir).unwrap().count());
+
+        let now = events[3].time;
+        let query = JournalQuery::parse(&args("--since 2023.09.17"), now).unwrap();
+        assert_eq!(events[1..].to_vec(), query.run(&dir));
+        let query = JournalQuery::parse(&args("--system ualx-3 --since 30h"), now).unwrap();
+        assert_eq!(events[1..2].to_vec(), query.run(&dir));
+        let query = JournalQuery::parse(&args("--type spawn --character alt"), now).unwrap();
+        assert!(query.run(&dir).is_empty());
+        let query = JournalQuery::parse(&args("--type intel --character reporter --channel EAST.IMPERIUM"), now).unwrap();
+        assert_eq!(events[2..3].to_vec(), query.run(&dir));
+        assert_eq!(vec![EventKind::FactionSpawn], JournalQuery::parse(&args("--type FactionSpawn"), now).unwrap().kinds);
+
+        assert!(JournalQuery::parse(&args("--since"), now).is_err());
+        assert!(JournalQuery::parse(&args("--since yesterday"), now).is_err());
+        assert!(JournalQuery::parse(&args("--colour red"), now).is_err());
+    }
+}
@@ -66,6 +66,10 @@ impl LogReader {
         self.character_name.to_owned()
     }
 
+    pub fn get_channel_name(&self) -> String {
+        self.channel_name.to_owned()
+    }
+
     pub fn get_log_file(&self) -> String {
         self.log_file.to_owned()
     }
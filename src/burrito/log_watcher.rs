@@ -1,18 +1,19 @@
-use std::{collections::HashMap, time::SystemTime, fs::DirEntry};
+use std::{collections::{BTreeMap, BTreeSet, HashMap}, time::SystemTime, fs::DirEntry};
 
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, NaiveDateTime, TimeZone, Utc};
 use enum_index_derive::{EnumIndex, IndexEnum};
 use regex::Regex;
 use serde_derive::{Serialize, Deserialize};
 
-use super::{systems::{SystemContext, SystemMap}, burrito_cfg::BurritoCfg, burrito_data::BurritoData, log_reader::LogReader, bloom_filter::BloomFilter};
+use super::{activity::ActivityMonitor, diagnostics, systems::{self, SystemContext, SystemMap}, burrito_cfg::{BurritoCfg, GameLogAlertMode}, burrito_data::BurritoData, log_reader::LogReader, bloom_filter::BloomFilter, damage::{self, DamageTracker}, mining::{self, MiningTracker}, ratting::{self, RattingTracker}};
 
 use enum_index::EnumIndex;
+use strum_macros::{Display, EnumDiscriminants, EnumIter, EnumString};
 
 //const TIMESTAMP_REGEX: &str = r#"\[\s[0-9]{4}\.[0-9]{2}\.[0-9]{2}\s[0-9]{2}:[0-9]{2}:[0-9]{2}\s\]"#;
 const CHAT_LOG_REGEX: &str = r#"(?<ts>\[ [0-9]{4}\.[0-9]{2}\.[0-9]{2} [0-9]{2}:[0-9]{2}:[0-9]{2} \]) (?<sender>.{1,}) > (?<content>.{1,})"#;
 const GAME_LOG_REGEX: &str = r#"(?<ts>\[ [0-9]{4}\.[0-9]{2}\.[0-9]{2} [0-9]{2}:[0-9]{2}:[0-9]{2} \]) \((?<type>[a-z]{1,})\) (?<content>.{1,})"#;
-//const TS_FMT: &str = "[ %Y.%m.%d %H:%M:%S ]";
+const TS_FMT: &str = "[ %Y.%m.%d %H:%M:%S ]";
 const SYSTEM_MESSAGE_SENDER: &str = "EVE System";
 const CHAT_CONNECTION_LOST_MESSAGE: &str = "Connection to chat server lost";
 const CHAT_CONNECTION_RESTORED_MESSAGE: &str = "Reconnected to chat server";
@@ -23,9 +24,15 @@ pub struct LogWatcher {
     data: BurritoData,
     log_readers: Vec<LogReader>,
     old_log_hashes: BloomFilter,
+    /// Log directories found missing, so each is only reported once
+    missing_log_dirs: BTreeSet<String>,
     recent_post_cache: HashMap<(String, String), i64>,
     sys_map: SystemMap,// TODO: should be &SystemMap
     log_events: LogEventQueue,
+    ratting: RattingTracker,
+    mining: MiningTracker,
+    damage: DamageTracker,
+    activity: ActivityMonitor,
 }
 
 #[derive(Clone, Debug, Deserialize, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize)]
@@ -35,6 +42,79 @@ pub struct LogEvent {
     pub event_type: EventType,
     pub trigger: String,
     pub message: String,
+    #[serde(default, flatten)]
+    pub details: EventDetails,
+}
+
+/// Extra information about where an event came from
+///
+/// Only chat log events fill these in; fields that do not apply are empty.
+#[derive(Clone, Debug, Default, Deserialize, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize)]
+pub struct EventDetails {
+    /// Chat channel the message was posted in
+    #[serde(default)]
+    pub channel: String,
+    /// Character that posted the message
+    #[serde(default)]
+    pub reporter: String,
+    /// System named in the message
+    #[serde(default)]
+    pub reported_system: String,
+    /// Watched system closest to `reported_system`
+    #[serde(default)]
+    pub watched_system: String,
+}
+
+/// Builder for the events used in tests
+#[cfg(test)]
+impl LogEvent {
+    /// Event of `Alt A` happening now, without trigger, message or details
+    pub fn test(event_type: EventType) -> Self {
+        Self {
+            time: Utc::now(),
+            character_name: "Alt A".to_owned(),
+            event_type,
+            trigger: String::new(),
+            message: String::new(),
+            details: EventDetails::default(),
+        }
+    }
+
+    pub fn with_time(self, time: DateTime<Utc>) -> Self {
+        Self { time, ..self }
+    }
+
+    pub fn with_character(self, character_name: impl Into<String>) -> Self {
+        Self { character_name: character_name.into(), ..self }
+    }
+
+    pub fn with_trigger(self, trigger: impl Into<String>) -> Self {
+        Self { trigger: trigger.into(), ..self }
+    }
+
+    pub fn with_message(self, message: impl Into<String>) -> Self {
+        Self { message: message.into(), ..self }
+    }
+
+    pub fn with_channel(mut self, channel: impl Into<String>) -> Self {
+        self.details.channel = channel.into();
+        self
+    }
+
+    pub fn with_reporter(mut self, reporter: impl Into<String>) -> Self {
+        self.details.reporter = reporter.into();
+        self
+    }
+
+    pub fn with_system(mut self, reported_system: impl Into<String>) -> Self {
+        self.details.reported_system = reported_system.into();
+        self
+    }
+
+    pub fn with_watched_system(mut self, watched_system: impl Into<String>) -> Self {
+        self.details.watched_system = watched_system.into();
+        self
+    }
 }
 
 impl LogWatcher {
@@ -44,16 +124,25 @@ impl LogWatcher {
         data: BurritoData,
         sys_map: SystemMap,
     ) -> Self {
-        let game_log_alert_cd_ms = cfg.game_log_alert_cd_ms;
+        let log_events = LogEventQueue::new(
+            cfg.game_log_alert_cd_ms,
+            cfg.game_log_alert_mode.clone(),
+            cfg.site_npc_timeout_ms,
+        );
         Self {
             ctx,
             cfg,
             data,
             log_readers: vec![],
             old_log_hashes: BloomFilter::new(),
+            missing_log_dirs: BTreeSet::new(),
             recent_post_cache: HashMap::new(),
             sys_map,
-            log_events: LogEventQueue { log_event_cd_ms: game_log_alert_cd_ms, last_log_event_ms: 0, log_events: vec![] },
+            log_events,
+            ratting: RattingTracker::new(),
+            mining: MiningTracker::new(),
+            damage: DamageTracker::new(),
+            activity: ActivityMonitor::new(),
         }
     }
 
@@ -63,9 +152,10 @@ impl LogWatcher {
     }
 
     pub fn get_events(&mut self) -> Vec<LogEvent> {
-        let new_log_readers = self.update_log_readers();
-        self.log_readers.extend(new_log_readers);
         let event_time = chrono::offset::Utc::now();
+        let new_log_readers = self.update_log_readers();
+        // Replaced logs are read one last time below, so their final lines are not lost
+        let replaced_readers = self.get_replaced_readers(&new_log_readers);
         self.update_recent_post_cache(event_time.timestamp_millis());
         for reader in &mut self.log_readers {
             let result = reader.read_new_lines();
@@ -90,6 +180,11 @@ impl LogWatcher {
                             self.recent_post_cache.insert(cache_key, event_time.timestamp_millis());
                         }
                         let results = self.ctx.process_message(content.to_owned(), &self.sys_map);
+                        let chat_details = EventDetails {
+                            channel: reader.get_channel_name(),
+                            reporter: sender.to_owned(),
+                            ..Default::default()
+                        };
                         match sender {
                             SYSTEM_MESSAGE_SENDER => {
                                 match content {
@@ -101,6 +196,7 @@ impl LogWatcher {
                                                 event_type: EventType::ChatConnectionLost,
                                                 trigger: line.to_owned(),
                                                 message: CHAT_CONNECTION_LOST_MESSAGE.to_owned(),
+                                                details: chat_details,
                                             }
                                         );
                                     }
@@ -112,6 +208,7 @@ impl LogWatcher {
                                                 event_type: EventType::ChatConnectionRestored,
                                                 trigger: line.to_owned(),
                                                 message: CHAT_CONNECTION_RESTORED_MESSAGE.to_owned(),
+                                                details: chat_details,
                                             }
                                         );
                                     }
@@ -123,17 +220,20 @@ impl LogWatcher {
                             _ => {
                                 let mut event_type = EventType::ChatlogMessage;
                                 let mut message = content.to_owned();
+                                let mut details = chat_details;
                                 if let Some(result) = results.iter().next() {
                                     let d = result.0.get_route();
+                                    details.reported_system = systems::find_reported_system(content, &self.sys_map).unwrap_or_default();
+                                    details.watched_system = self.sys_map.get_system_name(result.1).unwrap_or_default();
                                     let content_lower = content.to_lowercase().replace("?", "").replace(".", "");
                                     let content_lower = content_lower.trim();
                                     if content_lower.ends_with("clr") || content_lower.ends_with("clear") {
                                         event_type = EventType::SystemClear(d);
-                                        message = format!("System clear!");
+                                        message = format!("{} clear, {} jumps away from {}!", details.reported_system, d, details.watched_system);
                                     }
                                     else if content_lower.ends_with("status") || content_lower.ends_with("stat") {
                                         event_type = EventType::SystemStatusRequest(d);
-                                        message = format!("Status request!");
+                                        message = format!("Status request for {}, {} jumps away from {}!", details.reported_system, d, details.watched_system);
                                     }
                                     else {
                                         event_type = EventType::RangeOfSystem(d);
@@ -147,6 +247,7 @@ impl LogWatcher {
                                         event_type: event_type,
                                         trigger: line.to_owned(),
                                         message: message,
+                                        details,
                                     }
                                 );
                             }
@@ -158,56 +259,278 @@ impl LogWatcher {
                     if let Some(cap) = regex.captures(&line) {
                         let msg_type = &cap["type"];
                         let content = &cap["content"];
-                        if msg_type.to_lowercase() == "combat" {// TODO: rewrite as match for other cases
-                            for officer_name in self.data.officer_npc_alerts.to_owned() {
-                                if content.contains(&officer_name) {
-                                    self.log_events.push_game_log_event(
+                        let character_name = reader.get_character_name();
+                        self.activity.record_game_log_line(&character_name, event_time, &msg_type.to_lowercase(), content, &self.data);
+                        match msg_type.to_lowercase().as_str() {
+                            "combat" => {
+                                if let Some((damage, _)) = damage::parse_incoming_damage(content) {
+                                    self.damage.record_incoming(&character_name, event_time, damage);
+                                }
+                                let mut npc_names = vec![];
+                                for officer_name in &self.data.officer_npc_alerts {
+                                    if content.contains(officer_name) {
+                                        self.log_events.push_game_log_event(
+                                            LogEvent {
+                                                time: event_time,
+                                                character_name: character_name.to_owned(),
+                                                event_type: EventType::OfficerSpawn,
+                                                trigger: line.to_owned(),
+                                                message: format!("{} spawn!", officer_name),
+                                                details: EventDetails::default(),
+                                            },
+                                            officer_name,
+                                        );
+                                        npc_names.push(officer_name.to_owned());
+                                    }
+                                }
+                                for special_name in &self.data.special_npc_alerts {
+                                    if content.contains(special_name) {
+                                        let event_type = if special_name.contains("Titan") {
+                                            EventType::TitanSpawn
+                                        }
+                                        else {
+                                            EventType::DreadSpawn
+                                        };
+                                        self.log_events.push_game_log_event(
+                                            LogEvent {
+                                                time: event_time,
+                                                character_name: character_name.to_owned(),
+                                                event_type,
+                                                trigger: line.to_owned(),
+                                                message: format!("{} spawn!", special_name),
+                                                details: EventDetails::default(),
+                                            },
+                                            special_name,
+                                        );
+                                        npc_names.push(special_name.to_owned());
+                                    }
+                                }
+                                for faction_string in &self.data.faction_npc_alerts {
+                                    if content.contains(faction_string) {
+                                        self.log_events.push_game_log_event(
+                                            LogEvent {
+                                                time: event_time,
+                                                character_name: character_name.to_owned(),
+                                                event_type: EventType::FactionSpawn,
+                                                trigger: line.to_owned(),
+                                                message: format!("{} spawn!", faction_string),
+                                                details: EventDetails::default(),
+                                            },
+                                            faction_string,
+                                        );
+                                        npc_names.push(faction_string.to_owned());
+                                    }
+                                }
+                                self.log_events.set_last_combat_npcs(&character_name, npc_names);
+                            },
+                            "bounty" => {
+                                self.log_events.clear_killed_npcs(&character_name);
+                                if let Some(isk) = ratting::parse_bounty(content) {
+                                    self.ratting.record_bounty(&character_name, &reader.get_log_file(), event_time, isk);
+                                    self.log_events.push_chat_log_event(
                                         LogEvent {
                                             time: event_time,
-                                            character_name: reader.get_character_name(),
-                                            event_type: EventType::OfficerSpawn,
+                                            character_name: character_name.to_owned(),
+                                            event_type: EventType::Bounty,
                                             trigger: line.to_owned(),
-                                            message: format!("{} spawn!", officer_name).to_owned(),
+                                            message: format!("+{} ISK", ratting::format_isk(isk)),
+                                            details: EventDetails::default(),
                                         }
                                     );
                                 }
-                            }
-                            for special_name in self.data.special_npc_alerts.to_owned() {
-                                if content.contains(&special_name) {
-                                    self.log_events.push_game_log_event(
+                            },
+                            "mining" => {
+                                if let Some((ore, units)) = mining::parse_mining(content) {
+                                    let volume = mining::get_ore_volume(&ore, &self.data);
+                                    self.mining.record_cycle(&character_name, &ore, units, volume, event_time);
+                                    self.log_events.push_chat_log_event(
                                         LogEvent {
                                             time: event_time,
-                                            character_name: reader.get_character_name(),
-                                            event_type: EventType::DreadSpawn,
+                                            character_name: character_name.to_owned(),
+                                            event_type: EventType::MiningYield,
                                             trigger: line.to_owned(),
-                                            message: format!("{} spawn!", special_name).to_owned(),
+                                            message: format!("{} units of {}", units, ore),
+                                            details: EventDetails::default(),
                                         }
                                     );
                                 }
-                            }
-                            for faction_string in self.data.faction_npc_alerts.to_owned() {
-                                if content.contains(&faction_string) {
-                                    self.log_events.push_game_log_event(
+                            },
+                            "notify" => {
+                                if mining::is_hold_full_message(content, &self.data) {
+                                    self.log_events.push_game_log_notice(
                                         LogEvent {
                                             time: event_time,
-                                            character_name: reader.get_character_name(),
-                                            event_type: EventType::FactionSpawn,
+                                            character_name: character_name.to_owned(),
+                                            event_type: EventType::OreHoldFull,
                                             trigger: line.to_owned(),
-                                            message: format!("{} spawn!", faction_string).to_owned(),
+                                            message: "Ore hold full!".to_owned(),
+                                            details: EventDetails::default(),
                                         }
                                     );
                                 }
-                            }
+                                else if mining::is_asteroid_depleted_message(content, &self.data) {
+                                    self.log_events.push_game_log_notice(
+                                        LogEvent {
+                                            time: event_time,
+                                            character_name: character_name.to_owned(),
+                                            event_type: EventType::AsteroidDepleted,
+                                            trigger: line.to_owned(),
+                                            message: "Asteroid depleted!".to_owned(),
+                                            details: EventDetails::default(),
+                                        }
+                                    );
+                                }
+                            },
+                            _ => {},
                         }
                     }
                 }
             }
         }
+        self.end_sessions(replaced_readers, event_time);
+        self.log_readers.extend(new_log_readers);
+        let damage_alert_hits = self.damage.check_alerts(
+            event_time,
+            &self.cfg.damage_alert_config.damage_alerts,
+            &self.cfg.character_tags,
+        );
+        for hit in damage_alert_hits {
+            let message = format!("{:.0} DPS incoming over {}s!", hit.dps, hit.alert.window_ms as f64 / 1000.0);
+            self.log_events.push_chat_log_event(
+                LogEvent {
+                    time: event_time,
+                    character_name: hit.character_name,
+                    event_type: EventType::DamageThreshold(hit.alert.dps),
+                    trigger: message.to_owned(),
+                    message,
+                    details: EventDetails::default(),
+                }
+            );
+        }
+        if self.cfg.client_monitor_config.inactivity_alert_ms > 0 {
+            let inactive_ms = self.cfg.client_monitor_config.inactivity_alert_ms;
+            for character_name in self.activity.check_inactive(event_time, inactive_ms) {
+                let message = format!("No game log activity in space for {} minutes!", inactive_ms / 60000);
+                self.log_events.push_chat_log_event(
+                    LogEvent {
+                        time: event_time,
+                        character_name,
+                        event_type: EventType::ClientInactive,
+                        trigger: message.to_owned(),
+                        message,
+                        details: EventDetails::default(),
+                    }
+                );
+            }
+        }
+        if self.cfg.ratting_config.bounty_idle_alert_ms > 0 {
+            let idle_ms = self.cfg.ratting_config.bounty_idle_alert_ms;
+            for character_name in self.ratting.check_idle(event_time, idle_ms) {
+                let message = format!("No bounties for {} minutes!", idle_ms / 60000);
+                self.log_events.push_chat_log_event(
+                    LogEvent {
+                        time: event_time,
+                        character_name,
+                        event_type: EventType::BountyTicksStopped,
+                        trigger: message.to_owned(),
+                        message,
+                        details: EventDetails::default(),
+                    }
+                );
+            }
+        }
         let new_events = self.log_events.get_log_events().to_owned();
         self.log_events.log_events.clear();
         new_events
     }
 
+    /// Applies a reloaded config, keeping the readers and trackers
+    pub fn set_config(&mut self, cfg: BurritoCfg) {
+        self.log_events.set_config(cfg.game_log_alert_cd_ms, cfg.game_log_alert_mode.clone(), cfg.site_npc_timeout_ms);
+        self.cfg = cfg;
+    }
+
+    pub fn get_data(&self) -> &BurritoData {
+        &self.data
+    }
+
+    /// Applies a reloaded burrito.dat
+    pub fn set_data(&mut self, data: BurritoData) {
+        self.data = data;
+    }
+
+    pub fn get_context(&self) -> &SystemContext {
+        &self.ctx
+    }
+
+    /// Context for watching and unwatching systems and characters while running
+    pub fn get_context_mut(&mut self) -> &mut SystemContext {
+        &mut self.ctx
+    }
+
+    pub fn get_log_readers(&self) -> &[LogReader] {
+        &self.log_readers
+    }
+
+    pub fn get_activity_monitor(&self) -> &ActivityMonitor {
+        &self.activity
+    }
+
+    pub fn get_ratting_tracker(&self) -> &RattingTracker {
+        &self.ratting
+    }
+
+    pub fn get_mining_tracker(&self) -> &MiningTracker {
+        &self.mining
+    }
+
+    /// Readers whose log was replaced by a new session of the same client
+    ///
+    /// The client starts a new game log for every session, so a new game log for
+    /// a listener means the old session ended, usually because the client was
+    /// restarted or crashed.
+    fn get_replaced_readers(&self, new_log_readers: &[LogReader]) -> Vec<LogReader> {
+        let mut replaced: Vec<LogReader> = vec![];
+        for new_reader in new_log_readers {
+            let character_name = new_reader.get_character_name();
+            if character_name.is_empty() {
+                continue;
+            }
+            let old_readers = self.log_readers.iter()
+                .filter(|r| r.is_chatlog_reader() == new_reader.is_chatlog_reader()
+                    && r.get_character_name() == character_name
+                    && r.get_channel_name() == new_reader.get_channel_name()
+                    && r.get_log_file() != new_reader.get_log_file());
+            for old_reader in old_readers {
+                if !replaced.iter().any(|r| r.get_log_file() == old_reader.get_log_file()) {
+                    replaced.push(old_reader.clone());
+                }
+            }
+        }
+        replaced
+    }
+
+    /// Drops replaced readers, whose remaining lines have been read
+    fn end_sessions(&mut self, replaced_readers: Vec<LogReader>, event_time: DateTime<Utc>) {
+        for old_reader in replaced_readers {
+            self.log_readers.retain(|r| r.get_log_file() != old_reader.get_log_file());
+            if !old_reader.is_chatlog_reader() {
+                let character_name = old_reader.get_character_name();
+                self.activity.reset(&character_name);
+                self.log_events.push_chat_log_event(
+                    LogEvent {
+                        time: event_time,
+                        character_name,
+                        event_type: EventType::SessionEnded,
+                        trigger: old_reader.get_log_file(),
+                        message: "Client session ended, a new game log was started".to_owned(),
+                        details: EventDetails::default(),
+                    }
+                );
+            }
+        }
+    }
+
     fn update_recent_post_cache(&mut self, current_time_ms: i64) {
         let map = self.recent_post_cache.clone();
         let keys = map.keys();
@@ -225,10 +548,8 @@ impl LogWatcher {
         let mut chat_log_dir = game_log_dir.clone();
         game_log_dir.push_str("/Gamelogs/");
         chat_log_dir.push_str("/Chatlogs/");
-        let files = std::fs::read_dir(&game_log_dir)
-            .expect("Game log directory not found!");
+        let files = self.read_log_dir(&game_log_dir);
         files.into_iter().for_each(|file| {
-            let file = file.unwrap();
             let filename = file.file_name();
             let filename = filename.to_string_lossy();
             if filename.ends_with(".txt") {
@@ -243,10 +564,8 @@ impl LogWatcher {
                 }
             }
         });
-        let files = std::fs::read_dir(&chat_log_dir)
-            .expect("Chat log directory not found!");
+        let files = self.read_log_dir(&chat_log_dir);
         files.into_iter().for_each(|file| {
-            let file = file.unwrap();
             let filename = file.file_name();
             let filename = filename.to_str().unwrap();
             for channel in self.cfg.text_channel_config.text_channels.iter() {
@@ -266,14 +585,29 @@ impl LogWatcher {
         readers
     }
 
+    /// Files in a log directory, reporting the directory once while it is missing
+    fn read_log_dir(&mut self, dir: &str) -> Vec<DirEntry> {
+        match std::fs::read_dir(dir) {
+            Ok(files) => {
+                self.missing_log_dirs.remove(dir);
+                files.filter_map(|file| file.ok()).collect()
+            },
+            Err(e) => {
+                if self.missing_log_dirs.insert(dir.to_owned()) {
+                    diagnostics::report(format!("Unable to read log directory {dir}: {e}"));
+                }
+                vec![]
+            },
+        }
+    }
+
     fn ignore_old_logs_and_watch_recent(&mut self) {
         let mut game_log_dir = self.cfg.log_dir.to_owned();
         let mut chat_log_dir = game_log_dir.clone();
         game_log_dir.push_str("/Gamelogs/");
         chat_log_dir.push_str("/Chatlogs/");
-        let files = std::fs::read_dir(&game_log_dir)
-            .expect("Game log directory not found!");
-        files.into_iter().filter_map(|file| file.ok()).for_each(|file| {
+        let files = self.read_log_dir(&game_log_dir);
+        files.into_iter().for_each(|file| {
             let filename = file.file_name();
             let filename = filename.to_string_lossy();
             if modified_in_last_day(&file) {
@@ -288,9 +622,8 @@ impl LogWatcher {
             }
             self.old_log_hashes.insert(&filename);
         });
-        let files = std::fs::read_dir(&chat_log_dir)
-            .expect("Chat log directory not found!");
-        files.into_iter().filter_map(|file| file.ok()).for_each(|file| {
+        let files = self.read_log_dir(&chat_log_dir);
+        files.into_iter().for_each(|file| {
             let filename = file.file_name();
             let filename = filename.to_str().unwrap();
             if modified_in_last_day(&file) {
@@ -311,6 +644,61 @@ impl LogWatcher {
 
 }
 
+/// A line read back from a game log file
+#[derive(Clone, Debug)]
+pub struct GameLogLine {
+    pub time: DateTime<Utc>,
+    pub character_name: String,
+    pub log_file: String,
+    pub msg_type: String,
+    pub content: String,
+}
+
+/// Splits a game log line into its timestamp, message type and content
+pub fn parse_game_log_line(line: &str) -> Option<(DateTime<Utc>, String, String)> {
+    let regex = Regex::new(GAME_LOG_REGEX).unwrap();
+    let cap = regex.captures(line)?;
+    let time = Utc.from_utc_datetime(&NaiveDateTime::parse_from_str(&cap["ts"], TS_FMT).ok()?);
+    Some((time, cap["type"].to_lowercase(), cap["content"].to_owned()))
+}
+
+/// Reads the lines of the last `max_age_secs` from every game log modified in that time
+///
+/// Lines keep the timestamps written by the client, so this can be used to
+/// rebuild statistics for sessions that happened while Burrito was not running.
+pub fn read_game_log_history(log_dir: &str, max_age_secs: u64) -> Result<Vec<GameLogLine>, String> {
+    let mut game_log_dir = log_dir.to_owned();
+    game_log_dir.push_str("/Gamelogs/");
+    let since = chrono::Duration::try_seconds(max_age_secs as i64)
+        .and_then(|max_age| Utc::now().checked_sub_signed(max_age))
+        .unwrap_or(DateTime::<Utc>::MIN_UTC);
+    let mut lines = vec![];
+    let files = std::fs::read_dir(&game_log_dir)
+        .map_err(|e| format!("Unable to read game log directory {game_log_dir}: {e}"))?;
+    files.into_iter().filter_map(|file| file.ok()).for_each(|file| {
+        let filename = file.file_name();
+        let filename = filename.to_string_lossy();
+        if filename.ends_with(".txt") && get_modified_ago(&file) < max_age_secs {
+            let mut file_path = game_log_dir.clone();
+            file_path.push_str(&filename);
+            let mut reader = LogReader::new_gamelog_reader(&file_path);
+            for line in reader.read_new_lines().lines {
+                if let Some((time, msg_type, content)) = parse_game_log_line(&line).filter(|(time, _, _)| *time >= since) {
+                    lines.push(GameLogLine {
+                        time,
+                        character_name: reader.get_character_name(),
+                        log_file: reader.get_log_file(),
+                        msg_type,
+                        content,
+                    });
+                }
+            }
+        }
+    });
+    lines.sort_by(|a, b| a.time.cmp(&b.time));
+    Ok(lines)
+}
+
 fn modified_in_last_day(dir_entry: &DirEntry) -> bool {
     get_modified_ago(dir_entry) < 86400
 }
@@ -327,7 +715,12 @@ fn get_modified_ago(dir_entry: &DirEntry) -> u64 {
     return u64::MAX;
 }
 
-#[derive(Clone, Debug, EnumIndex, IndexEnum, Deserialize, Eq, Hash, PartialEq, Serialize)]
+/// Kinds of events, ignoring the data some event types carry
+///
+/// `EventKind::RangeOfSystem` covers every `EventType::RangeOfSystem(_)` and so on.
+/// This is what alert rules match on.
+#[derive(Clone, Debug, EnumIndex, EnumDiscriminants, IndexEnum, Deserialize, Eq, Hash, PartialEq, Serialize)]
+#[strum_discriminants(name(EventKind), derive(Deserialize, Display, EnumIter, EnumString, Hash, Ord, PartialOrd, Serialize))]
 pub enum EventType {
     RangeOfSystem(u32),
     RangeOfCharacter(u32),
@@ -342,6 +735,14 @@ pub enum EventType {
     SystemChangedMessage,
     ChatConnectionLost,
     ChatConnectionRestored,
+    Bounty,
+    BountyTicksStopped,
+    MiningYield,
+    OreHoldFull,
+    AsteroidDepleted,
+    DamageThreshold(u32),
+    ClientInactive,
+    SessionEnded,
 }
 
 use std::cmp::Ordering;
@@ -376,11 +777,34 @@ impl Ord for EventType {
                     _ => self.enum_index().cmp(&other.enum_index()),
                 }
             },
+            EventType::DamageThreshold(x) => {
+                match other {
+                    EventType::DamageThreshold(y) => x.cmp(y),
+                    _ => self.enum_index().cmp(&other.enum_index()),
+                }
+            },
             _ => self.enum_index().cmp(&other.enum_index()),
         }
     }
 }
 
+impl EventType {
+    pub fn get_kind(&self) -> EventKind {
+        self.into()
+    }
+
+    /// Number of jumps for events reported relative to a watched system
+    pub fn get_distance(&self) -> Option<u32> {
+        match self {
+            EventType::RangeOfSystem(d)
+            | EventType::RangeOfCharacter(d)
+            | EventType::SystemClear(d)
+            | EventType::SystemStatusRequest(d) => Some(*d),
+            _ => None,
+        }
+    }
+}
+
 impl PartialOrd for EventType {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
         Some(self.cmp(other))
@@ -404,29 +828,109 @@ pub enum GameMessageType {// TODO: make more comprehensive list of these
     NeutOutgoing,
 }
 
+/// Key used to track game log alerts: (character name, event type, NPC name)
+type GameLogEventKey = (String, EventType, String);
+
 #[derive(Clone, Default, Deserialize, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize)]
 pub struct LogEventQueue {
     log_event_cd_ms: u64,
-    last_log_event_ms: u64,
+    mode: GameLogAlertMode,
+    site_npc_timeout_ms: u64,
+    /// Time of the last alert for each key in `GameLogAlertMode::Cooldown`
+    last_log_event_ms: BTreeMap<GameLogEventKey, u64>,
+    /// Time each NPC was last seen in a combat line in `GameLogAlertMode::OncePerSite`
+    site_npcs_last_seen_ms: BTreeMap<GameLogEventKey, u64>,
+    /// NPCs mentioned in the most recent combat line of each character
+    last_combat_npcs: BTreeMap<String, Vec<GameLogEventKey>>,
     log_events: Vec<LogEvent>,
 }
 
 impl LogEventQueue {
-    pub fn new(log_event_cd_ms: u64) -> Self {
+    pub fn new(log_event_cd_ms: u64, mode: GameLogAlertMode, site_npc_timeout_ms: u64) -> Self {
         Self {
             log_event_cd_ms,
-            last_log_event_ms: 0u64,
+            mode,
+            site_npc_timeout_ms,
+            last_log_event_ms: BTreeMap::new(),
+            site_npcs_last_seen_ms: BTreeMap::new(),
+            last_combat_npcs: BTreeMap::new(),
             log_events: vec![],
         }
     }
-    pub fn push_game_log_event(&mut self, log_event: LogEvent) {
-        let now = Utc::now().timestamp_millis() as u64;
-        if now - self.last_log_event_ms < self.log_event_cd_ms {
-            return;
+
+    /// Changes the alert settings, keeping the cooldowns that are running
+    pub fn set_config(&mut self, log_event_cd_ms: u64, mode: GameLogAlertMode, site_npc_timeout_ms: u64) {
+        self.log_event_cd_ms = log_event_cd_ms;
+        self.mode = mode;
+        self.site_npc_timeout_ms = site_npc_timeout_ms;
+    }
+    /// Pushes an event caused by `npc_name` appearing in a game log
+    ///
+    /// Cooldowns are tracked separately for every character, event type and NPC
+    /// name, so a spawn on one client never silences a different spawn on another.
+    pub fn push_game_log_event(&mut self, log_event: LogEvent, npc_name: &str) {
+        let now = log_event.time.timestamp_millis() as u64;
+        let key = (log_event.character_name.to_owned(), log_event.event_type.to_owned(), npc_name.to_owned());
+        match self.mode {
+            GameLogAlertMode::Cooldown => {
+                if !self.start_cooldown(key, now) {
+                    return;
+                }
+            },
+            GameLogAlertMode::OncePerSite => {
+                let timeout = self.site_npc_timeout_ms;
+                if timeout > 0 {
+                    self.site_npcs_last_seen_ms.retain(|_, last_seen| now.saturating_sub(*last_seen) < timeout);
+                }
+                if self.site_npcs_last_seen_ms.insert(key, now).is_some() {
+                    // Still the same NPC; it was already alerted on
+                    return;
+                }
+            },
         }
-        self.last_log_event_ms = now;
         self.push_chat_log_event(log_event);
     }
+    /// Pushes a game log event that is not caused by an NPC, such as a full ore hold
+    ///
+    /// These are limited by `game_log_alert_cd_ms` per character and event type
+    /// in either mode, since there is no NPC whose site could end.
+    pub fn push_game_log_notice(&mut self, log_event: LogEvent) {
+        let now = log_event.time.timestamp_millis() as u64;
+        let key = (log_event.character_name.to_owned(), log_event.event_type.to_owned(), String::new());
+        if self.start_cooldown(key, now) {
+            self.push_chat_log_event(log_event);
+        }
+    }
+    /// Starts the cooldown of `key` unless it is still running, returns whether it started
+    fn start_cooldown(&mut self, key: GameLogEventKey, now: u64) -> bool {
+        if let Some(last) = self.last_log_event_ms.get(&key) {
+            if now.saturating_sub(*last) < self.log_event_cd_ms {
+                return false;
+            }
+        }
+        self.last_log_event_ms.insert(key, now);
+        true
+    }
+    /// Remembers which NPCs were named in the latest combat line of a character
+    pub fn set_last_combat_npcs(&mut self, character_name: &str, npc_names: Vec<String>) {
+        let keys = self.site_npcs_last_seen_ms.keys()
+            .filter(|key| key.0 == character_name && npc_names.contains(&key.2))
+            .cloned()
+            .collect();
+        self.last_combat_npcs.insert(character_name.to_owned(), keys);
+    }
+    /// Handles a bounty tick for a character
+    ///
+    /// A bounty is paid out right after the final blow, so the NPCs named in the
+    /// character's last combat line are considered gone and will alert again if
+    /// they are seen afterwards.
+    pub fn clear_killed_npcs(&mut self, character_name: &str) {
+        if let Some(keys) = self.last_combat_npcs.remove(character_name) {
+            for key in keys {
+                self.site_npcs_last_seen_ms.remove(&key);
+            }
+        }
+    }
     pub fn push_chat_log_event(&mut self, log_event: LogEvent) {
         self.log_events.push(log_event);
     }
@@ -471,7 +975,7 @@ pub enum IntelChannel {
 }
 
 impl IntelChannel {
-    fn get_channel(&self) -> String {
+    pub fn get_channel(&self) -> String {
         match self {
             IntelChannel::Aridia => "aridia.imperium".to_owned(),
             IntelChannel::Branch => "brn.imperium".to_owned(),
@@ -508,7 +1012,15 @@ impl IntelChannel {
     }
 }
 
+#[cfg(test)]
 mod test {
+    use std::{fs, io::Write};
+
+    use chrono::Duration;
+
+    use crate::burrito::utils::TestDir;
+
+    use super::*;
 
    #[test]
     fn test_log_event_ord() {
@@ -521,6 +1033,126 @@ mod test {
         assert!(EventType::RangeOfSystem(4) > EventType::RangeOfCharacter(0));
         assert!(EventType::SystemClear(69) < EventType::SystemClear(420));
         assert!(EventType::SystemStatusRequest(322) < EventType::SystemStatusRequest(9001));
+        assert!(EventType::DamageThreshold(300) < EventType::DamageThreshold(500));
+    }
+
+    fn spawn_event(character_name: &str, event_type: EventType, time_ms: i64) -> LogEvent {
+        LogEvent::test(event_type)
+            .with_time(Utc.timestamp_millis_opt(time_ms).unwrap())
+            .with_character(character_name)
+    }
+
+    #[test]
+    fn test_game_log_cooldown_per_key() {
+        let mut uut = LogEventQueue::new(5000, GameLogAlertMode::Cooldown, 0);
+        uut.push_game_log_event(spawn_event("Alt A", EventType::FactionSpawn, 1000), "Dread Guristas");
+        // Different character, event type and NPC are not affected by the cooldown
+        uut.push_game_log_event(spawn_event("Alt B", EventType::OfficerSpawn, 1100), "Thon Eney");
+        uut.push_game_log_event(spawn_event("Alt A", EventType::FactionSpawn, 1200), "Domination");
+        // Same key within the cooldown is dropped
+        uut.push_game_log_event(spawn_event("Alt A", EventType::FactionSpawn, 5999), "Dread Guristas");
+        assert_eq!(3, uut.get_log_events().len());
+        uut.push_game_log_event(spawn_event("Alt A", EventType::FactionSpawn, 6000), "Dread Guristas");
+        assert_eq!(4, uut.get_log_events().len());
+    }
+
+    #[test]
+    fn test_game_log_once_per_site() {
+        let mut uut = LogEventQueue::new(5000, GameLogAlertMode::OncePerSite, 60000);
+        uut.push_game_log_event(spawn_event("Alt A", EventType::FactionSpawn, 0), "Dread Guristas");
+        uut.set_last_combat_npcs("Alt A", vec!["Dread Guristas".to_owned()]);
+        // Long fight with the same NPC only alerts once
+        for t in 1..10 {
+            uut.push_game_log_event(spawn_event("Alt A", EventType::FactionSpawn, t * 30000), "Dread Guristas");
+            uut.set_last_combat_npcs("Alt A", vec!["Dread Guristas".to_owned()]);
+        }
+        assert_eq!(1, uut.get_log_events().len());
+        // Bounty tick after the last combat line naming it means it died
+        uut.clear_killed_npcs("Alt A");
+        uut.push_game_log_event(spawn_event("Alt A", EventType::FactionSpawn, 300001), "Dread Guristas");
+        assert_eq!(2, uut.get_log_events().len());
+        // A bounty for a different NPC does not clear it
+        uut.set_last_combat_npcs("Alt A", vec![]);
+        uut.clear_killed_npcs("Alt A");
+        uut.push_game_log_event(spawn_event("Alt A", EventType::FactionSpawn, 300002), "Dread Guristas");
+        assert_eq!(2, uut.get_log_events().len());
+        // Not seen for longer than the timeout
+        uut.push_game_log_event(spawn_event("Alt A", EventType::FactionSpawn, 400000), "Dread Guristas");
+        assert_eq!(3, uut.get_log_events().len());
+    }
+
+    #[test]
+    fn test_game_log_notice_once_per_site() {
+        let mut uut = LogEventQueue::new(5000, GameLogAlertMode::OncePerSite, 0);
+        uut.push_game_log_notice(spawn_event("Alt A", EventType::OreHoldFull, 0));
+        // Repeated within the cooldown
+        uut.push_game_log_notice(spawn_event("Alt A", EventType::OreHoldFull, 3000));
+        assert_eq!(1, uut.get_log_events().len());
+        // A full hold after unloading alerts again, although no site timeout is set
+        uut.push_game_log_notice(spawn_event("Alt A", EventType::OreHoldFull, 600000));
+        uut.push_game_log_notice(spawn_event("Alt A", EventType::AsteroidDepleted, 600000));
+        assert_eq!(3, uut.get_log_events().len());
+    }
+
+    #[test]
+    fn test_replaced_game_log_is_read_to_the_end() {
+        let dir = TestDir::new("sessions");
+        fs::create_dir_all(dir.join("Gamelogs")).unwrap();
+        fs::create_dir_all(dir.join("Chatlogs")).unwrap();
+        let header = "------------------------------------------------------------\r\n  Gamelog\r\n  Listener: Alt A\r\n------------------------------------------------------------\r\n";
+        let old_log = dir.join("Gamelogs").join("20231001_120000.txt");
+        fs::write(&old_log, header).unwrap();
+        let mut cfg = BurritoCfg { log_dir: dir.get_path(""), ..Default::default() };
+        cfg.text_channel_config.text_channels.clear();
+        let mut uut = LogWatcher::new(SystemContext::default(), cfg, BurritoData::default(), SystemMap::default());
+        uut.init();
+
+        // The client writes a last line and is restarted before the next update
+        let mut file = fs::OpenOptions::new().append(true).open(&old_log).unwrap();
+        file.write_all(b"[ 2023.10.01 12:30:00 ] (notify) Your ore hold is full.\r\n").unwrap();
+        fs::write(dir.join("Gamelogs").join("20231001_123100.txt"), header).unwrap();
+        let events: Vec<EventType> = uut.get_events().into_iter().map(|event| event.event_type).collect();
+        assert_eq!(vec![EventType::OreHoldFull, EventType::SessionEnded], events);
+        assert_eq!(1, uut.get_log_readers().len());
+    }
+
+    #[test]
+    fn test_missing_game_log_dir() {
+        let dir = TestDir::new("missing_game_logs");
+        fs::create_dir_all(dir.join("Chatlogs")).unwrap();
+        let mut cfg = BurritoCfg { log_dir: dir.get_path(""), ..Default::default() };
+        cfg.text_channel_config.text_channels.clear();
+        let mut uut = LogWatcher::new(SystemContext::default(), cfg, BurritoData::default(), SystemMap::default());
+        uut.init();
+        assert!(uut.get_events().is_empty());
+        assert!(uut.get_events().is_empty());
+
+        // Picked up once the client creates it
+        fs::create_dir_all(dir.join("Gamelogs")).unwrap();
+        let log = "------------------------------------------------------------\r\n  Gamelog\r\n  Listener: Alt A\r\n------------------------------------------------------------\r\n";
+        fs::write(dir.join("Gamelogs").join("20231001_120000.txt"), log).unwrap();
+        assert!(uut.get_events().is_empty());
+        assert_eq!(1, uut.get_log_readers().len());
+    }
+
+    #[test]
+    fn test_read_game_log_history() {
+        let dir = TestDir::new("history");
+        let log_dir = dir.get_path("");
+        assert!(read_game_log_history(&dir.get_path("missing"), 3600).is_err());
+
+        fs::create_dir_all(dir.join("Gamelogs")).unwrap();
+        let now = Utc::now();
+        let old = (now - Duration::hours(2)).format(TS_FMT);
+        let recent = (now - Duration::minutes(5)).format(TS_FMT);
+        let log = format!("------------------------------------------------------------\r\n  Gamelog\r\n  Listener: Alt A\r\n------------------------------------------------------------\r\n\
+            {old} (bounty) <font size=12><b><color=0xff00aa00>10,000 ISK</b> added to next bounty payout\r\n\
+            {recent} (bounty) <font size=12><b><color=0xff00aa00>20,000 ISK</b> added to next bounty payout\r\n");
+        fs::write(dir.join("Gamelogs").join("20231001_120000.txt"), log).unwrap();
+        let lines = read_game_log_history(&log_dir, 3600).unwrap();
+        assert_eq!(1, lines.len());
+        assert!(lines[0].content.contains("20,000 ISK"));
+        assert_eq!(2, read_game_log_history(&log_dir, 3 * 3600).unwrap().len());
     }
 
 }
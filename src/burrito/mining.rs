@@ -0,0 +1,170 @@
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, Utc};
+use regex::Regex;
+
+use super::{burrito_data::BurritoData, log_watcher::GameLogLine, utils};
+
+const MINING_REGEX: &str = r"mined (an additional )?(?<units>[0-9][0-9,]*) units of (?<ore>[^.]+)";
+
+/// Yield of a single ore type for one character
+#[derive(Clone, Debug, PartialEq)]
+pub struct OreYield {
+    pub ore: String,
+    pub first_cycle: DateTime<Utc>,
+    pub last_cycle: DateTime<Utc>,
+    pub cycles: u64,
+    pub units: u64,
+    /// Total volume, if the volume of the ore is known
+    pub volume_m3: Option<f64>,
+}
+
+impl OreYield {
+    pub fn get_m3_per_hour(&self) -> Option<f64> {
+        m3_per_hour(self.volume_m3?, (self.last_cycle - self.first_cycle).num_milliseconds())
+    }
+}
+
+/// Aggregates mining cycles per character and per ore type
+#[derive(Clone, Debug, Default)]
+pub struct MiningTracker {
+    characters: BTreeMap<String, BTreeMap<String, OreYield>>,
+}
+
+impl MiningTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Builds a tracker from the `(mining)` lines of previously written game logs
+    pub fn from_game_log_lines(lines: &[GameLogLine], data: &BurritoData) -> Self {
+        let mut tracker = Self::new();
+        for line in lines.iter().filter(|line| line.msg_type == "mining") {
+            if let Some((ore, units)) = parse_mining(&line.content) {
+                let volume = get_ore_volume(&ore, data);
+                tracker.record_cycle(&line.character_name, &ore, units, volume, line.time);
+            }
+        }
+        tracker
+    }
+
+    pub fn record_cycle(&mut self, character_name: &str, ore: &str, units: u64, unit_volume_m3: Option<f64>, time: DateTime<Utc>) {
+        let ores = self.characters.entry(character_name.to_owned()).or_default();
+        let ore_yield = ores.entry(ore.to_owned()).or_insert_with(|| OreYield {
+            ore: ore.to_owned(),
+            first_cycle: time,
+            last_cycle: time,
+            cycles: 0,
+            units: 0,
+            volume_m3: unit_volume_m3.map(|_| 0.0),
+        });
+        ore_yield.last_cycle = time;
+        ore_yield.cycles += 1;
+        ore_yield.units += units;
+        if let (Some(volume), Some(unit_volume)) = (ore_yield.volume_m3.as_mut(), unit_volume_m3) {
+            *volume += units as f64 * unit_volume;
+        }
+    }
+
+    pub fn get_characters(&self) -> Vec<String> {
+        self.characters.keys().cloned().collect()
+    }
+
+    pub fn get_ore_yields(&self, character_name: &str) -> Vec<OreYield> {
+        self.characters.get(character_name)
+            .map(|ores| ores.values().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    /// Total volume mined by a character, counting only ores with a known volume
+    pub fn get_total_m3(&self, character_name: &str) -> f64 {
+        self.get_ore_yields(character_name).iter().filter_map(|y| y.volume_m3).sum()
+    }
+
+    /// m3/hour for a character from its first to its last cycle
+    pub fn get_m3_per_hour(&self, character_name: &str) -> Option<f64> {
+        let yields = self.get_ore_yields(character_name);
+        let first = yields.iter().map(|y| y.first_cycle).min()?;
+        let last = yields.iter().map(|y| y.last_cycle).max()?;
+        m3_per_hour(self.get_total_m3(character_name), (last - first).num_milliseconds())
+    }
+
+}
+
+/// Extracts the ore type and number of units from the content of a `(mining)` game log line
+pub fn parse_mining(content: &str) -> Option<(String, u64)> {
+    let regex = Regex::new(MINING_REGEX).unwrap();
+    let content = utils::strip_tags(content);
+    let cap = regex.captures(&content)?;
+    let units = cap["units"].replace(',', "").parse().ok()?;
+    Some((cap["ore"].trim().to_owned(), units))
+}
+
+/// Looks up the volume of one unit of ore
+///
+/// Ore variants such as "Dense Veldspar" use the volume of their base ore, so
+/// the longest known name contained in `ore` wins.
+pub fn get_ore_volume(ore: &str, data: &BurritoData) -> Option<f64> {
+    data.ore_volumes.iter()
+        .filter(|(name, _)| ore.contains(name.as_str()))
+        .max_by_key(|(name, _)| name.len())
+        .map(|(_, volume)| *volume)
+}
+
+pub fn is_hold_full_message(content: &str, data: &BurritoData) -> bool {
+    contains_any(content, &data.mining_hold_full_messages)
+}
+
+pub fn is_asteroid_depleted_message(content: &str, data: &BurritoData) -> bool {
+    contains_any(content, &data.asteroid_depleted_messages)
+}
+
+fn contains_any(content: &str, messages: &[String]) -> bool {
+    let content = utils::strip_tags(content).to_lowercase();
+    messages.iter().any(|m| content.contains(&m.to_lowercase()))
+}
+
+fn m3_per_hour(volume_m3: f64, duration_ms: i64) -> Option<f64> {
+    if duration_ms <= 0 {
+        return None;
+    }
+    Some(volume_m3 * 3_600_000.0 / duration_ms as f64)
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{TimeZone, Utc};
+
+    use crate::burrito::burrito_data::BurritoData;
+
+    use super::{get_ore_volume, parse_mining, MiningTracker, is_hold_full_message};
+
+    #[test]
+    fn test_parse_mining() {
+        assert_eq!(
+            Some(("Veldspar".to_owned(), 242)),
+            parse_mining("You mined <color=#ff8dc169>242</color><color=#77ffffff><font size=12> units of </font></color><color=#ffffffff><font size=12>Veldspar</font></color>"),
+        );
+        assert_eq!(
+            Some(("Dense Veldspar".to_owned(), 1204)),
+            parse_mining("Critical mining success! You mined an additional 1,204 units of Dense Veldspar."),
+        );
+        assert_eq!(None, parse_mining("Your mining laser deactivates"));
+    }
+
+    #[test]
+    fn test_mining_tracker() {
+        let data = BurritoData::default();
+        assert_eq!(Some(0.1), get_ore_volume("Dense Veldspar", &data));
+        assert_eq!(None, get_ore_volume("Unobtainium", &data));
+        assert!(is_hold_full_message("<color=0xffffffff>Your ore hold is full.", &data));
+
+        let mut uut = MiningTracker::new();
+        uut.record_cycle("Alt A", "Veldspar", 1000, Some(0.1), Utc.timestamp_millis_opt(0).unwrap());
+        uut.record_cycle("Alt A", "Veldspar", 1000, Some(0.1), Utc.timestamp_millis_opt(60_000).unwrap());
+        uut.record_cycle("Alt A", "Unobtainium", 5, None, Utc.timestamp_millis_opt(60_000).unwrap());
+        assert_eq!(200.0, uut.get_total_m3("Alt A"));
+        assert_eq!(Some(12000.0), uut.get_m3_per_hour("Alt A"));
+        assert_eq!(2, uut.get_ore_yields("Alt A").len());
+    }
+}
@@ -1,13 +1,34 @@
 
+pub mod activity;
 pub mod alert;
+pub mod alert_rules;
+pub mod api;
+pub mod audio;
 pub mod bloom_filter;
 pub mod burrito_cfg;
 pub mod burrito_data;
+pub mod cfg_check;
+pub mod coalesce;
+pub mod commands;
+pub mod control;
+pub mod damage;
+pub mod diagnostics;
+pub mod dashboard;
+pub mod escalation;
+pub mod exec;
+pub mod journal;
 pub mod json_struct;
 pub mod log_reader;
 pub mod log_watcher;
+pub mod mining;
+pub mod mutes;
+pub mod notifier;
 pub mod path_cache;
+pub mod ratting;
+pub mod reload;
 pub mod serde_utils;
 pub mod systems;
+pub mod tones;
 pub mod types;
 pub mod utils;
+pub mod webhook;
@@ -0,0 +1,388 @@
+use std::{fmt::Display, fs, io::ErrorKind, path::{Path, PathBuf}, time::SystemTime};
+
+use chrono::{DateTime, Duration, Local, NaiveTime, Utc};
+use serde_derive::{Deserialize, Serialize};
+use strum::IntoEnumIterator;
+
+use super::{cfg_check, diagnostics, log_watcher::{EventKind, LogEvent}, utils::{self, parse_duration}};
+
+const MUTES_FILE: &str = "mutes.json";
+
+/// Event kinds covered by a category name
+///
+/// Besides the categories below, any event type name like `FactionSpawn` can be used.
+pub fn get_category_kinds(category: &str) -> Result<Vec<EventKind>, String> {
+    let kinds = match category.to_lowercase().as_str() {
+        "all" => EventKind::iter().collect(),
+        "intel" => vec![EventKind::RangeOfSystem, EventKind::RangeOfCharacter, EventKind::SystemClear, EventKind::SystemStatusRequest],
+        "spawn" => vec![EventKind::FactionSpawn, EventKind::DreadSpawn, EventKind::TitanSpawn, EventKind::OfficerSpawn],
+        "ratting" => vec![EventKind::Bounty, EventKind::BountyTicksStopped],
+        "mining" => vec![EventKind::MiningYield, EventKind::OreHoldFull, EventKind::AsteroidDepleted],
+        "client" => vec![EventKind::ChatConnectionLost, EventKind::ChatConnectionRestored, EventKind::ClientInactive, EventKind::SessionEnded],
+        _ => match EventKind::iter().find(|kind| kind.to_string().eq_ignore_ascii_case(category)) {
+            Some(kind) => vec![kind],
+            None => return Err(format!("Unknown category or event type: {category}")),
+        },
+    };
+    Ok(kinds)
+}
+
+/// Whether `categories` and `beyond_jumps` of a mute or quiet hours cover an event
+fn covers(categories: &[String], beyond_jumps: Option<u32>, event: &LogEvent) -> bool {
+    let kind = event.event_type.get_kind();
+    let in_category = categories.is_empty() || categories.iter()
+        .any(|category| get_category_kinds(category).map(|kinds| kinds.contains(&kind)).unwrap_or(false));
+    match beyond_jumps {
+        Some(jumps) => in_category && event.event_type.get_distance().map(|d| d > jumps).unwrap_or(false),
+        None => in_category,
+    }
+}
+
+fn format_filter(categories: &[String], beyond_jumps: Option<u32>) -> String {
+    let categories = if categories.is_empty() { "all".to_owned() } else { categories.join(",") };
+    match beyond_jumps {
+        Some(jumps) => format!("{categories} beyond {jumps} jumps"),
+        None => categories,
+    }
+}
+
+/// Parses a time of day like `08:00`
+fn parse_time(text: &str) -> Result<NaiveTime, String> {
+    NaiveTime::parse_from_str(text, "%H:%M").map_err(|_| format!("Invalid time of day: {text}, expected e.g. 08:00"))
+}
+
+/// Alerts muted until a point in time
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct Mute {
+    /// Categories or event types that are muted, all alerts if empty
+    #[serde(default)]
+    pub categories: Vec<String>,
+    /// Only mute events more than this many jumps away
+    #[serde(default)]
+    pub beyond_jumps: Option<u32>,
+    pub until: DateTime<Utc>,
+}
+
+impl Mute {
+    /// Parses the arguments of `burrito mute`, e.g. `spawn 30m` or `intel beyond 2 until 08:00`
+    ///
+    /// Times of day are local and refer to their next occurrence after `now`.
+    pub fn parse(args: &[String], now: DateTime<Local>) -> Result<Self, String> {
+        let (categories, rest) = match args.split_first() {
+            Some((categories, rest)) => (categories, rest),
+            None => return Err("Specify what to mute, e.g. `spawn 30m` or `intel beyond 2 until 08:00`".to_owned()),
+        };
+        let categories: Vec<String> = categories.split(',').map(|c| c.trim().to_owned()).filter(|c| !c.is_empty()).collect();
+        for category in &categories {
+            get_category_kinds(category)?;
+        }
+        let mut beyond_jumps = None;
+        let mut until = None;
+        let mut words = rest.iter().map(|w| w.as_str());
+        while let Some(word) = words.next() {
+            match word {
+                "beyond" => {
+                    let jumps = words.next().ok_or("`beyond` requires a number of jumps")?;
+                    beyond_jumps = Some(jumps.parse().map_err(|_| format!("Invalid number of jumps: {jumps}"))?);
+                },
+                "until" => {
+                    let time = parse_time(words.next().ok_or("`until` requires a time of day")?)?;
+                    let mut end = now.date_naive().and_time(time).and_local_timezone(Local).earliest()
+                        .ok_or(format!("{time} does not exist today"))?;
+                    if end <= now {
+                        end += Duration::days(1);
+                    }
+                    until = Some(end.with_timezone(&Utc));
+                },
+                "for" => {
+                    let duration = words.next().ok_or("`for` requires a duration")?;
+                    until = Some(now.with_timezone(&Utc) + parse_duration(duration).ok_or(format!("Invalid duration: {duration}"))?);
+                },
+                duration => {
+                    until = Some(now.with_timezone(&Utc) + parse_duration(duration).ok_or(format!("Unrecognized argument: {duration}"))?);
+                },
+            }
+        }
+        let until = until.ok_or("Specify how long to mute, e.g. `30m` or `until 08:00`")?;
+        Ok(Self {
+            categories: if categories.iter().any(|c| c.eq_ignore_ascii_case("all")) { vec![] } else { categories },
+            beyond_jumps,
+            until,
+        })
+    }
+
+    pub fn covers(&self, event: &LogEvent, now: DateTime<Utc>) -> bool {
+        now < self.until && covers(&self.categories, self.beyond_jumps, event)
+    }
+}
+
+impl Display for Mute {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} until {}", format_filter(&self.categories, self.beyond_jumps), self.until.with_timezone(&Local).format("%Y.%m.%d %H:%M"))
+    }
+}
+
+/// Daily period in which alerts are muted, in local time
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct QuietHours {
+    /// Start time like `23:00`
+    pub start: String,
+    /// End time like `07:00`, may be before `start` to span midnight
+    pub end: String,
+    /// Categories or event types that are muted, all alerts if empty
+    #[serde(default)]
+    pub categories: Vec<String>,
+    /// Only mute events more than this many jumps away
+    #[serde(default)]
+    pub beyond_jumps: Option<u32>,
+}
+
+impl QuietHours {
+    pub fn validate(&self) -> Result<(), String> {
+        parse_time(&self.start)?;
+        parse_time(&self.end)?;
+        for category in &self.categories {
+            get_category_kinds(category)?;
+        }
+        Ok(())
+    }
+
+    pub fn is_active(&self, now: DateTime<Local>) -> bool {
+        let (start, end) = match (parse_time(&self.start), parse_time(&self.end)) {
+            (Ok(start), Ok(end)) => (start, end),
+            _ => return false,
+        };
+        let time = now.time();
+        if start <= end {
+            start <= time && time < end
+        }
+        else {
+            time >= start || time < end
+        }
+    }
+
+    pub fn covers(&self, event: &LogEvent, now: DateTime<Local>) -> bool {
+        self.is_active(now) && covers(&self.categories, self.beyond_jumps, event)
+    }
+}
+
+impl Display for QuietHours {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} daily from {} to {}", format_filter(&self.categories, self.beyond_jumps), self.start, self.end)
+    }
+}
+
+/// Mutes set with `burrito mute`, shared with running instances through `mutes.json`
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct Mutes {
+    #[serde(default)]
+    pub mutes: Vec<Mute>,
+    /// When the file was last written, to notice changes by other instances
+    #[serde(skip)]
+    modified: Option<SystemTime>,
+}
+
+impl Mutes {
+    /// Reads `mutes.json`, without any mutes if it does not exist
+    pub fn read() -> Result<Self, String> {
+        Self::read_from(&get_path())
+    }
+
+    /// Reads `mutes.json`, reporting an invalid file and starting without mutes
+    pub fn load_from_file() -> Self {
+        Self::read().unwrap_or_else(|e| {
+            diagnostics::report(e);
+            Self::default()
+        })
+    }
+
+    fn read_from(path: &Path) -> Result<Self, String> {
+        let modified = get_modified(path);
+        let mut mutes: Self = match fs::read_to_string(path) {
+            Ok(text) => serde_json::from_str(&text)
+                .map_err(|e| format!("Invalid {}: {}", path.display(), cfg_check::describe_json_error(&e)))?,
+            Err(e) if e.kind() == ErrorKind::NotFound => Self::default(),
+            Err(e) => return Err(format!("Unable to read {}: {e}", path.display())),
+        };
+        mutes.modified = modified;
+        Ok(mutes)
+    }
+
+    /// Writes the mutes that have not expired yet
+    pub fn save(&mut self) {
+        if let Err(e) = self.save_to(&get_path()) {
+            diagnostics::report(e);
+        }
+    }
+
+    /// Writes a temporary file and renames it, so other instances never read a
+    /// partly written file
+    fn save_to(&mut self, path: &Path) -> Result<(), String> {
+        let now = Utc::now();
+        self.mutes.retain(|mute| mute.until > now);
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(|e| format!("Unable to create {}: {e}", dir.display()))?;
+        }
+        let temp_path = path.with_extension("json.tmp");
+        fs::write(&temp_path, serde_json::to_string_pretty(self).unwrap())
+            .and_then(|_| fs::rename(&temp_path, path))
+            .map_err(|e| format!("Unable to save {}: {e}", path.display()))?;
+        self.modified = get_modified(path);
+        Ok(())
+    }
+
+    /// Loads the file again if another instance changed it, returns whether it did
+    ///
+    /// An invalid file is reported once and the previous mutes are kept.
+    pub fn reload_if_changed(&mut self) -> bool {
+        self.reload_from(&get_path())
+    }
+
+    fn reload_from(&mut self, path: &Path) -> bool {
+        let modified = get_modified(path);
+        if modified == self.modified {
+            return false;
+        }
+        match Self::read_from(path) {
+            Ok(mutes) => {
+                *self = mutes;
+                true
+            },
+            Err(e) => {
+                diagnostics::report(format!("{e}, keeping the previous mutes"));
+                self.modified = modified;
+                false
+            },
+        }
+    }
+
+    pub fn add(&mut self, mute: Mute) {
+        self.mutes.push(mute);
+    }
+
+    /// Removes every mute, or a category from the mutes, returns how many mutes changed
+    ///
+    /// A mute is removed once its last category is gone.
+    pub fn remove(&mut self, category: Option<&str>) -> usize {
+        let category = match category {
+            Some(category) => category,
+            None => {
+                let count = self.mutes.len();
+                self.mutes.clear();
+                return count;
+            },
+        };
+        let mut count = 0;
+        self.mutes.retain_mut(|mute| {
+            if !mute.categories.iter().any(|c| c.eq_ignore_ascii_case(category)) {
+                return true;
+            }
+            count += 1;
+            mute.categories.retain(|c| !c.eq_ignore_ascii_case(category));
+            !mute.categories.is_empty()
+        });
+        count
+    }
+
+    pub fn get_active(&self, now: DateTime<Utc>) -> Vec<&Mute> {
+        self.mutes.iter().filter(|mute| mute.until > now).collect()
+    }
+
+    /// Whether a mute or the quiet hours silence the alert for an event
+    pub fn is_muted(&self, event: &LogEvent, quiet_hours: &[QuietHours], now: DateTime<Local>) -> bool {
+        self.mutes.iter().any(|mute| mute.covers(event, now.with_timezone(&Utc)))
+            || quiet_hours.iter().any(|quiet_hours| quiet_hours.covers(event, now))
+    }
+}
+
+fn get_path() -> PathBuf {
+    Path::new(&utils::get_burrito_dir()).join(MUTES_FILE)
+}
+
+fn get_modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|metadata| metadata.modified()).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, Local, TimeZone, Utc};
+
+    use crate::burrito::{log_watcher::{EventType, LogEvent}, utils::TestDir};
+
+    use super::{Mute, Mutes, QuietHours};
+
+    fn args(line: &str) -> Vec<String> {
+        line.split_whitespace().map(|w| w.to_owned()).collect()
+    }
+
+    #[test]
+    fn test_parse_mute() {
+        let now = Local.with_ymd_and_hms(2023, 9, 18, 12, 0, 0).unwrap();
+        let mute = Mute::parse(&args("spawn 30m"), now).unwrap();
+        assert_eq!(vec!["spawn".to_owned()], mute.categories);
+        assert_eq!(now + Duration::minutes(30), mute.until);
+
+        let mute = Mute::parse(&args("intel beyond 2 until 08:00"), now).unwrap();
+        assert_eq!(Some(2), mute.beyond_jumps);
+        assert_eq!(Local.with_ymd_and_hms(2023, 9, 19, 8, 0, 0).unwrap(), mute.until);
+        assert_eq!(now + Duration::minutes(90), Mute::parse(&args("all for 1h30m"), now).unwrap().until);
+        assert!(Mute::parse(&args("all for 1h30m"), now).unwrap().categories.is_empty());
+
+        assert!(Mute::parse(&args("spawn"), now).is_err());
+        assert!(Mute::parse(&args("spawns 30m"), now).is_err());
+        assert!(Mute::parse(&args("spawn until 25:00"), now).is_err());
+        assert!(Mute::parse(&args("intel beyond two 30m"), now).is_err());
+    }
+
+    #[test]
+    fn test_is_muted() {
+        let now = Local.with_ymd_and_hms(2023, 9, 18, 23, 30, 0).unwrap();
+        let mut uut = Mutes::default();
+        uut.add(Mute::parse(&args("spawn,ClientInactive 30m"), now).unwrap());
+        uut.add(Mute::parse(&args("intel beyond 2 30m"), now).unwrap());
+        assert!(uut.is_muted(&LogEvent::test(EventType::FactionSpawn), &[], now));
+        assert!(uut.is_muted(&LogEvent::test(EventType::ClientInactive), &[], now));
+        assert!(!uut.is_muted(&LogEvent::test(EventType::SessionEnded), &[], now));
+        assert!(uut.is_muted(&LogEvent::test(EventType::RangeOfSystem(3)), &[], now));
+        assert!(!uut.is_muted(&LogEvent::test(EventType::RangeOfSystem(2)), &[], now));
+        // Expired
+        assert!(!uut.is_muted(&LogEvent::test(EventType::FactionSpawn), &[], now + Duration::minutes(30)));
+
+        let quiet_hours = [QuietHours { start: "23:00".to_owned(), end: "07:00".to_owned(), categories: vec![], beyond_jumps: None }];
+        assert!(uut.is_muted(&LogEvent::test(EventType::SessionEnded), &quiet_hours, now));
+        assert!(uut.is_muted(&LogEvent::test(EventType::SessionEnded), &quiet_hours, now + Duration::hours(7)));
+        assert!(!uut.is_muted(&LogEvent::test(EventType::SessionEnded), &quiet_hours, now + Duration::hours(8)));
+
+        assert_eq!(1, uut.remove(Some("SPAWN")));
+        assert!(!uut.is_muted(&LogEvent::test(EventType::FactionSpawn), &[], now));
+        assert!(uut.is_muted(&LogEvent::test(EventType::ClientInactive), &[], now));
+        assert_eq!(1, uut.remove(Some("clientinactive")));
+        assert!(!uut.is_muted(&LogEvent::test(EventType::ClientInactive), &[], now));
+        assert_eq!(1, uut.remove(None));
+    }
+
+    #[test]
+    fn test_reload() {
+        let dir = TestDir::new("mutes");
+        let path = dir.join("mutes.json");
+        let now = Local::now();
+        let mut uut = Mutes::read_from(&path).unwrap();
+        assert!(uut.mutes.is_empty());
+        // Reading never creates the file
+        assert!(!path.exists());
+
+        let mut other = Mutes::default();
+        other.add(Mute::parse(&args("spawn 30m"), now).unwrap());
+        other.save_to(&path).unwrap();
+        assert!(uut.reload_from(&path));
+        assert_eq!(1, uut.get_active(now.with_timezone(&Utc)).len());
+        assert!(!uut.reload_from(&path));
+
+        // Half written by something else
+        std::thread::sleep(std::time::Duration::from_millis(20));
+        std::fs::write(&path, "{\"mutes\": [{\"categories\": [\"spa").unwrap();
+        assert!(!uut.reload_from(&path));
+        assert_eq!(1, uut.get_active(now.with_timezone(&Utc)).len());
+        assert!(Mutes::read_from(&path).is_err());
+    }
+}
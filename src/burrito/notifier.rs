@@ -0,0 +1,362 @@
+use std::{collections::BTreeMap, fs::OpenOptions, io::Write};
+
+use super::{alert::{self, OutputFormat}, alert_rules::AlertAction, audio::{AudioEngine, Sound, SoundCache, SoundRequest}, burrito_cfg::BurritoCfg, diagnostics, exec::ExecNotifier, log_watcher::LogEvent, tones::Tone, webhook::WebhookNotifier};
+
+/// Something that can tell the user about an event
+///
+/// Alert rule actions are dispatched to notifiers by name. Burrito registers its
+/// built-in notifiers in `Notifiers::with_defaults`, and programs built on top
+/// of this crate can register their own and select them with the `Custom` action.
+pub trait Notifier {
+    fn notify(&mut self, event: &LogEvent, action: &AlertAction) -> Result<(), String>;
+}
+
+/// Registry of notifiers, keyed by the name actions refer to them by
+#[derive(Default)]
+pub struct Notifiers {
+    notifiers: BTreeMap<String, Box<dyn Notifier>>,
+}
+
+impl Notifiers {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registry with every built-in notifier
+    ///
+    /// The sound files of the alert rules are decoded up front, and any that
+    /// cannot be played are reported.
+    pub fn with_defaults(cfg: &BurritoCfg) -> Self {
+        let (sounds, errors) = SoundCache::load(&cfg.alert_config.get_sound_files());
+        errors.into_iter().for_each(diagnostics::report);
+        let mut notifiers = Self::new();
+        notifiers.register("terminal", Box::new(TerminalNotifier::new(cfg.output_format)));
+        notifiers.register("audio", Box::new(AudioNotifier::new(AudioEngine::new(&cfg.sound_config, sounds))));
+        notifiers.register("desktop", Box::new(DesktopNotifier::new()));
+        notifiers.register("bell", Box::new(BellNotifier::new(cfg.output_format)));
+        notifiers.register("file", Box::new(FileNotifier));
+        notifiers.register("webhook", Box::new(WebhookNotifier::new()));
+        notifiers.register("exec", Box::new(ExecNotifier::new()));
+        notifiers
+    }
+
+    /// Adds a notifier, replacing any notifier already registered with that name
+    pub fn register(&mut self, name: &str, notifier: Box<dyn Notifier>) {
+        self.notifiers.insert(name.to_owned(), notifier);
+    }
+
+    pub fn get_names(&self) -> Vec<String> {
+        self.notifiers.keys().cloned().collect()
+    }
+
+    /// Runs every action for an event
+    ///
+    /// A failing notifier never stops the other actions from running; its error
+    /// is reported instead.
+    pub fn notify(&mut self, event: &LogEvent, actions: &[AlertAction]) {
+        for action in actions {
+            let name = action.get_notifier_name();
+            match self.notifiers.get_mut(name) {
+                Some(notifier) => {
+                    if let Err(e) = notifier.notify(event, action) {
+                        diagnostics::report(format!("Notifier {name} failed: {e}"));
+                    }
+                },
+                None => diagnostics::report(format!("No notifier named {name} is registered")),
+            }
+        }
+    }
+}
+
+/// Prints coloured alerts to stdout
+///
+/// With JSON output every event is already printed as JSON, so alerts are not
+/// printed again.
+pub struct TerminalNotifier {
+    format: OutputFormat,
+}
+
+impl TerminalNotifier {
+    pub fn new(format: OutputFormat) -> Self {
+        Self { format }
+    }
+}
+
+impl Notifier for TerminalNotifier {
+    fn notify(&mut self, event: &LogEvent, _action: &AlertAction) -> Result<(), String> {
+        if self.format == OutputFormat::Text {
+            alert::print_event(event);
+        }
+        Ok(())
+    }
+}
+
+/// Plays the sound file of a `Sound` action or the tone of a `Tone` action
+pub struct AudioNotifier {
+    engine: AudioEngine,
+}
+
+impl AudioNotifier {
+    pub fn new(engine: AudioEngine) -> Self {
+        Self { engine }
+    }
+}
+
+impl Notifier for AudioNotifier {
+    fn notify(&mut self, event: &LogEvent, action: &AlertAction) -> Result<(), String> {
+        let (sound, volume, priority) = match action {
+            // Sound files that could not be loaded fall back to the tone of the event type
+            AlertAction::Sound { file, volume, priority } if !self.engine.has_file(file) => {
+                (Sound::Tone(Tone::for_event_kind(&event.event_type.get_kind())), volume, priority)
+            },
+            AlertAction::Sound { file, volume, priority } => (Sound::File(file.to_owned()), volume, priority),
+            AlertAction::Tone { tone, volume, priority } => (Sound::Tone(*tone), volume, priority),
+            _ => return Ok(()),
+        };
+        self.engine.play(SoundRequest { sound, volume: *volume, priority: *priority })
+    }
+}
+
+/// Rings the terminal bell, through stderr with JSON output to keep stdout clean
+pub struct BellNotifier {
+    format: OutputFormat,
+}
+
+impl BellNotifier {
+    pub fn new(format: OutputFormat) -> Self {
+        Self { format }
+    }
+}
+
+impl Notifier for BellNotifier {
+    fn notify(&mut self, _event: &LogEvent, _action: &AlertAction) -> Result<(), String> {
+        let mut out: Box<dyn Write> = match self.format {
+            OutputFormat::Text => Box::new(std::io::stdout()),
+            OutputFormat::Json => Box::new(std::io::stderr()),
+        };
+        write!(out, "\x07").and_then(|_| out.flush()).map_err(|e| e.to_string())
+    }
+}
+
+/// Appends alerts to the file of a `Log` action
+pub struct FileNotifier;
+
+impl Notifier for FileNotifier {
+    fn notify(&mut self, event: &LogEvent, action: &AlertAction) -> Result<(), String> {
+        if let AlertAction::Log { file } = action {
+            let mut f = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(file)
+                .map_err(|e| format!("Unable to open {file}: {e}"))?;
+            writeln!(f, "[ {} ] {}", event.time.format("%Y.%m.%d %H:%M:%S"), alert::format_event(event))
+                .map_err(|e| format!("Unable to write to {file}: {e}"))?;
+        }
+        Ok(())
+    }
+}
+
+/// Sends freedesktop desktop notifications over D-Bus
+///
+/// Notifications are sent from a background thread, so a notification service
+/// that hangs does not hold up log processing.
+pub struct DesktopNotifier {
+    /// Bus to connect to, or the session bus if unset
+    address: Option<String>,
+    /// Summary and body of notifications waiting to be sent
+    #[cfg(all(unix, not(target_os = "macos")))]
+    queue: Option<std::sync::mpsc::Sender<(String, String)>>,
+}
+
+impl DesktopNotifier {
+    pub fn new() -> Self {
+        Self {
+            address: None,
+            #[cfg(all(unix, not(target_os = "macos")))]
+            queue: None,
+        }
+    }
+
+    /// Notifier that talks to the bus at `address` instead of the session bus
+    pub fn with_address(address: &str) -> Self {
+        Self {
+            address: Some(address.to_owned()),
+            #[cfg(all(unix, not(target_os = "macos")))]
+            queue: None,
+        }
+    }
+}
+
+impl Default for DesktopNotifier {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+impl DesktopNotifier {
+    /// Queue of the notification thread, starting the thread on first use
+    fn get_queue(&mut self) -> &std::sync::mpsc::Sender<(String, String)> {
+        let address = self.address.clone();
+        self.queue.get_or_insert_with(|| {
+            let (sender, receiver) = std::sync::mpsc::channel::<(String, String)>();
+            std::thread::spawn(move || {
+                let mut connection = None;
+                for (summary, body) in receiver {
+                    if let Err(e) = send_desktop_notification(&mut connection, address.as_deref(), &summary, &body) {
+                        diagnostics::report(e);
+                    }
+                }
+            });
+            sender
+        })
+    }
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+impl Notifier for DesktopNotifier {
+    fn notify(&mut self, event: &LogEvent, _action: &AlertAction) -> Result<(), String> {
+        let summary = format!("Burrito: {}", event.event_type.get_kind());
+        let body = alert::format_event(event);
+        self.get_queue().send((summary, body))
+            .map_err(|_| "Desktop notification thread is not running".to_owned())
+    }
+}
+
+/// Sends one notification, connecting to the bus first if there is no connection
+#[cfg(all(unix, not(target_os = "macos")))]
+fn send_desktop_notification(connection: &mut Option<zbus::blocking::Connection>, address: Option<&str>,
+    summary: &str, body: &str) -> Result<(), String> {
+    use std::collections::HashMap;
+    use zbus::zvariant::Value;
+
+    if connection.is_none() {
+        let new_connection = match address {
+            Some(address) => zbus::blocking::ConnectionBuilder::address(address)
+                .and_then(|builder| builder.build()),
+            None => zbus::blocking::Connection::session(),
+        };
+        *connection = Some(new_connection.map_err(|e| format!("Unable to connect to D-Bus: {e}"))?);
+    }
+    let actions: Vec<&str> = vec![];
+    let hints: HashMap<&str, Value> = HashMap::new();
+    let result = connection.as_ref().unwrap().call_method(
+        Some("org.freedesktop.Notifications"),
+        "/org/freedesktop/Notifications",
+        Some("org.freedesktop.Notifications"),
+        "Notify",
+        &("Burrito", 0u32, "", summary, body, actions, hints, -1i32),
+    );
+    if let Err(e) = result {
+        // Reconnect next time in case the bus went away
+        *connection = None;
+        return Err(format!("Unable to send desktop notification: {e}"));
+    }
+    Ok(())
+}
+
+#[cfg(not(all(unix, not(target_os = "macos"))))]
+impl Notifier for DesktopNotifier {
+    fn notify(&mut self, _event: &LogEvent, _action: &AlertAction) -> Result<(), String> {
+        Err("Desktop notifications are only supported on Linux and BSD".to_owned())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, rc::Rc};
+
+    use crate::burrito::{alert_rules::AlertAction, burrito_cfg::BurritoCfg, log_watcher::{EventType, LogEvent}, utils::TestDir};
+
+    use super::{Notifier, Notifiers};
+
+    fn event() -> LogEvent {
+        LogEvent::test(EventType::FactionSpawn)
+            .with_trigger("Dread Guristas Eliminator")
+            .with_message("Dread Guristas spawn!")
+    }
+
+    struct RecordingNotifier(Rc<RefCell<Vec<String>>>);
+
+    impl Notifier for RecordingNotifier {
+        fn notify(&mut self, event: &LogEvent, _action: &AlertAction) -> Result<(), String> {
+            self.0.borrow_mut().push(event.message.to_owned());
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_custom_notifier() {
+        let received = Rc::new(RefCell::new(vec![]));
+        let mut uut = Notifiers::new();
+        uut.register("recording", Box::new(RecordingNotifier(received.clone())));
+        uut.notify(&event(), &[AlertAction::Custom { notifier: "recording".to_owned() }, AlertAction::Bell]);
+        assert_eq!(vec!["Dread Guristas spawn!".to_owned()], *received.borrow());
+    }
+
+    #[test]
+    fn test_file_notifier() {
+        let dir = TestDir::new("file_notifier");
+        let file = dir.get_path("alerts.log");
+        let mut uut = Notifiers::with_defaults(&BurritoCfg::default());
+        uut.notify(&event(), &[AlertAction::Log { file: file.to_owned() }]);
+        uut.notify(&event(), &[AlertAction::Log { file: file.to_owned() }]);
+        let contents = std::fs::read_to_string(&file).unwrap();
+        assert_eq!(2, contents.lines().count());
+        assert!(contents.lines().all(|l| l.ends_with("[Alt A] Dread Guristas Eliminator")));
+    }
+
+    #[cfg(all(unix, not(target_os = "macos")))]
+    #[test]
+    #[ignore = "needs dbus-daemon, run with `cargo test -- --ignored`"]
+    fn test_desktop_notifier() {
+        use std::{collections::HashMap, io::{BufRead, BufReader}, process::{Command, Stdio}, sync::{Arc, Mutex}};
+        use zbus::zvariant::OwnedValue;
+        use super::DesktopNotifier;
+
+        struct MockNotifications {
+            received: Arc<Mutex<Vec<(String, String)>>>,
+        }
+
+        #[zbus::dbus_interface(name = "org.freedesktop.Notifications")]
+        impl MockNotifications {
+            #[allow(clippy::too_many_arguments)]
+            fn notify(&self, _app_name: String, _replaces_id: u32, _app_icon: String, summary: String, body: String,
+                _actions: Vec<String>, _hints: HashMap<String, OwnedValue>, _expire_timeout: i32) -> u32 {
+                self.received.lock().unwrap().push((summary, body));
+                1
+            }
+        }
+
+        // Private bus so the test never shows up on the desktop
+        let daemon = Command::new("dbus-daemon")
+            .args(["--session", "--nofork", "--print-address=1"])
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn();
+        let mut daemon = daemon.expect("dbus-daemon not found");
+        let mut address = String::new();
+        BufReader::new(daemon.stdout.take().unwrap()).read_line(&mut address).unwrap();
+        let address = address.trim();
+
+        let received = Arc::new(Mutex::new(vec![]));
+        let _server = zbus::blocking::ConnectionBuilder::address(address).unwrap()
+            .name("org.freedesktop.Notifications").unwrap()
+            .serve_at("/org/freedesktop/Notifications", MockNotifications { received: received.clone() }).unwrap()
+            .build().unwrap();
+
+        let mut uut = DesktopNotifier::with_address(address);
+        let result = uut.notify(&event(), &AlertAction::Desktop);
+        // The notification is sent from the notifier's thread
+        let deadline = std::time::Instant::now() + std::time::Duration::from_secs(5);
+        while received.lock().unwrap().is_empty() && std::time::Instant::now() < deadline {
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        }
+        _ = daemon.kill();
+        assert_eq!(Ok(()), result);
+        assert_eq!(
+            vec![("Burrito: FactionSpawn".to_owned(), "[Alt A] Dread Guristas Eliminator".to_owned())],
+            *received.lock().unwrap(),
+        );
+    }
+}
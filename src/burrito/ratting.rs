@@ -0,0 +1,204 @@
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, Utc};
+use regex::Regex;
+
+use super::{log_watcher::GameLogLine, utils};
+
+const BOUNTY_REGEX: &str = r"(?<isk>[0-9][0-9,]*(\.[0-9]+)?) ISK";
+
+/// Bounty statistics for a single game log session
+#[derive(Clone, Debug, PartialEq)]
+pub struct RattingSession {
+    pub log_file: String,
+    pub first_tick: DateTime<Utc>,
+    pub last_tick: DateTime<Utc>,
+    pub ticks: u64,
+    pub total_isk: u64,
+}
+
+impl RattingSession {
+    pub fn get_duration_ms(&self) -> i64 {
+        (self.last_tick - self.first_tick).num_milliseconds()
+    }
+
+    pub fn get_isk_per_hour(&self) -> Option<f64> {
+        isk_per_hour(self.total_isk, self.get_duration_ms())
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+struct CharacterRatting {
+    sessions: Vec<RattingSession>,
+    idle_alerted: bool,
+}
+
+/// Aggregates bounty ticks per character and per session
+#[derive(Clone, Debug, Default)]
+pub struct RattingTracker {
+    characters: BTreeMap<String, CharacterRatting>,
+}
+
+impl RattingTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Builds a tracker from the `(bounty)` lines of previously written game logs
+    pub fn from_game_log_lines(lines: &[GameLogLine]) -> Self {
+        let mut tracker = Self::new();
+        for line in lines.iter().filter(|line| line.msg_type == "bounty") {
+            if let Some(isk) = parse_bounty(&line.content) {
+                tracker.record_bounty(&line.character_name, &line.log_file, line.time, isk);
+            }
+        }
+        tracker
+    }
+
+    pub fn record_bounty(&mut self, character_name: &str, log_file: &str, time: DateTime<Utc>, isk: u64) {
+        let character = self.characters.entry(character_name.to_owned()).or_default();
+        character.idle_alerted = false;
+        match character.sessions.iter_mut().find(|s| s.log_file == log_file) {
+            Some(session) => {
+                session.last_tick = time;
+                session.ticks += 1;
+                session.total_isk += isk;
+            },
+            None => {
+                character.sessions.push(RattingSession {
+                    log_file: log_file.to_owned(),
+                    first_tick: time,
+                    last_tick: time,
+                    ticks: 1,
+                    total_isk: isk,
+                });
+            },
+        }
+    }
+
+    /// Returns the characters whose bounty ticks stopped more than `idle_ms` ago
+    ///
+    /// Each character is only returned once until it receives another bounty.
+    pub fn check_idle(&mut self, now: DateTime<Utc>, idle_ms: u64) -> Vec<String> {
+        let mut idle = vec![];
+        for (character_name, character) in self.characters.iter_mut() {
+            if character.idle_alerted {
+                continue;
+            }
+            if let Some(last_tick) = character.sessions.iter().map(|s| s.last_tick).max() {
+                if (now - last_tick).num_milliseconds() >= idle_ms as i64 {
+                    character.idle_alerted = true;
+                    idle.push(character_name.to_owned());
+                }
+            }
+        }
+        idle
+    }
+
+    pub fn get_characters(&self) -> Vec<String> {
+        self.characters.keys().cloned().collect()
+    }
+
+    pub fn get_sessions(&self, character_name: &str) -> Vec<RattingSession> {
+        self.characters.get(character_name)
+            .map(|c| c.sessions.to_owned())
+            .unwrap_or_default()
+    }
+
+    pub fn get_total_isk(&self, character_name: &str) -> u64 {
+        self.get_sessions(character_name).iter().map(|s| s.total_isk).sum()
+    }
+
+    /// ISK/hour for a character over all of its sessions
+    pub fn get_isk_per_hour(&self, character_name: &str) -> Option<f64> {
+        let sessions = self.get_sessions(character_name);
+        let duration_ms = sessions.iter().map(|s| s.get_duration_ms()).sum();
+        isk_per_hour(self.get_total_isk(character_name), duration_ms)
+    }
+
+    /// One line per character, used for the periodic summary
+    pub fn summary(&self) -> Vec<String> {
+        self.get_characters().iter().map(|character_name| {
+            format!(
+                "[{}] Ratting: {} ISK total, {} ISK/hour",
+                character_name,
+                format_isk(self.get_total_isk(character_name)),
+                format_isk_per_hour(self.get_isk_per_hour(character_name)),
+            )
+        }).collect()
+    }
+
+}
+
+/// Extracts the ISK amount from the content of a `(bounty)` game log line
+pub fn parse_bounty(content: &str) -> Option<u64> {
+    let regex = Regex::new(BOUNTY_REGEX).unwrap();
+    let content = utils::strip_tags(content);
+    let cap = regex.captures(&content)?;
+    let isk: f64 = cap["isk"].replace(',', "").parse().ok()?;
+    Some(isk.round() as u64)
+}
+
+/// Formats an ISK amount like the client does for large values (e.g. `12.35M`)
+pub fn format_isk(isk: u64) -> String {
+    let isk = isk as f64;
+    if isk >= 1_000_000_000.0 {
+        format!("{:.2}B", isk / 1_000_000_000.0)
+    }
+    else if isk >= 1_000_000.0 {
+        format!("{:.2}M", isk / 1_000_000.0)
+    }
+    else if isk >= 1_000.0 {
+        format!("{:.2}K", isk / 1_000.0)
+    }
+    else {
+        format!("{}", isk)
+    }
+}
+
+pub fn format_isk_per_hour(isk_per_hour: Option<f64>) -> String {
+    match isk_per_hour {
+        Some(rate) => format_isk(rate.round() as u64),
+        None => "-".to_owned(),
+    }
+}
+
+fn isk_per_hour(total_isk: u64, duration_ms: i64) -> Option<f64> {
+    if duration_ms <= 0 {
+        return None;
+    }
+    Some(total_isk as f64 * 3_600_000.0 / duration_ms as f64)
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{TimeZone, Utc};
+
+    use super::{parse_bounty, RattingTracker};
+
+    #[test]
+    fn test_parse_bounty() {
+        assert_eq!(Some(33787), parse_bounty("<font size=12><b><color=0xff00aa00>33,787 ISK</b><color=0x77ffffff><font size=10> added to next bounty payout"));
+        assert_eq!(Some(1250000), parse_bounty("1,250,000.00 ISK added to next bounty payout"));
+        assert_eq!(None, parse_bounty("added to next bounty payout"));
+    }
+
+    #[test]
+    fn test_isk_per_hour_and_idle() {
+        let mut uut = RattingTracker::new();
+        let start = Utc.timestamp_millis_opt(0).unwrap();
+        let half_hour = Utc.timestamp_millis_opt(1_800_000).unwrap();
+        uut.record_bounty("Alt A", "a.txt", start, 10_000_000);
+        uut.record_bounty("Alt A", "a.txt", half_hour, 10_000_000);
+        assert_eq!(Some(40_000_000.0), uut.get_isk_per_hour("Alt A"));
+        assert_eq!(1, uut.get_sessions("Alt A").len());
+
+        let later = Utc.timestamp_millis_opt(1_800_000 + 600_000).unwrap();
+        assert_eq!(vec!["Alt A".to_owned()], uut.check_idle(later, 600_000));
+        // Only alerted once per stop
+        assert!(uut.check_idle(later, 600_000).is_empty());
+        uut.record_bounty("Alt A", "b.txt", later, 1);
+        assert!(uut.check_idle(later, 600_000).is_empty());
+        assert_eq!(2, uut.get_sessions("Alt A").len());
+    }
+}
@@ -0,0 +1,186 @@
+use std::{collections::HashMap, fs, path::{Path, PathBuf}, time::SystemTime};
+
+use serde::Serialize;
+use serde_json::Value;
+use strum::IntoEnumIterator;
+use strum_macros::{Display, EnumIter};
+
+use super::utils;
+
+/// Longest value that is shown in full when it changes
+const MAX_VALUE_LEN: usize = 60;
+
+/// A file a running Burrito reloads when it is edited
+#[derive(Clone, Copy, Debug, Display, EnumIter, Eq, Hash, PartialEq)]
+pub enum ConfigFile {
+    #[strum(serialize = "burrito.cfg")]
+    Cfg,
+    #[strum(serialize = "burrito.dat")]
+    Data,
+    #[strum(serialize = "ctx.json")]
+    Context,
+}
+
+/// Notices when config files are edited, by their modification time
+///
+/// A file only counts as changed once its modification time has stayed the
+/// same for one check, so a file an editor is still writing is not read.
+pub struct ConfigWatcher {
+    dir: PathBuf,
+    /// Modification time of each file when it was last reported
+    loaded: HashMap<ConfigFile, Option<SystemTime>>,
+    /// Modification time of each file at the last check
+    seen: HashMap<ConfigFile, Option<SystemTime>>,
+}
+
+impl ConfigWatcher {
+    /// Watches the files in `.burrito/`
+    pub fn open_default() -> Self {
+        Self::new(PathBuf::from(utils::get_burrito_dir()))
+    }
+
+    /// Watches the files in `dir`, which are taken as loaded as they are now
+    pub fn new(dir: PathBuf) -> Self {
+        let loaded: HashMap<ConfigFile, Option<SystemTime>> = ConfigFile::iter()
+            .map(|file| (file, get_modified(&dir.join(file.to_string()))))
+            .collect();
+        Self {
+            dir,
+            seen: loaded.clone(),
+            loaded,
+        }
+    }
+
+    /// Files that were modified since the last call
+    pub fn get_changed(&mut self) -> Vec<ConfigFile> {
+        let mut changed = vec![];
+        for file in ConfigFile::iter() {
+            let modified = get_modified(&self.dir.join(file.to_string()));
+            let stable = self.seen.insert(file, modified) == Some(modified);
+            if stable && self.loaded.get(&file) != Some(&modified) {
+                self.loaded.insert(file, modified);
+                changed.push(file);
+            }
+        }
+        changed
+    }
+
+    /// Takes `file` as loaded as of `modified`, e.g. after writing it ourselves
+    pub fn set_loaded(&mut self, file: ConfigFile, modified: Option<SystemTime>) {
+        self.loaded.insert(file, modified);
+    }
+}
+
+fn get_modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|metadata| metadata.modified()).ok()
+}
+
+/// Describes how `new` differs from `old`, one line per changed setting
+///
+/// Lists that only differ in order count as unchanged, since most of them are
+/// sets whose order is not kept.
+pub fn get_changes<T: Serialize>(old: &T, new: &T) -> Vec<String> {
+    let mut changes = vec![];
+    match (serde_json::to_value(old), serde_json::to_value(new)) {
+        (Ok(old), Ok(new)) => add_changes("", &old, &new, &mut changes),
+        (Err(e), _) | (_, Err(e)) => changes.push(format!("Unable to compare: {e}")),
+    }
+    changes
+}
+
+fn add_changes(path: &str, old: &Value, new: &Value, changes: &mut Vec<String>) {
+    let child_path = |key: &str| if path.is_empty() { key.to_owned() } else { format!("{path}.{key}") };
+    match (old, new) {
+        (Value::Object(old), Value::Object(new)) => {
+            for (key, old_value) in old {
+                add_changes(&child_path(key), old_value, new.get(key).unwrap_or(&Value::Null), changes);
+            }
+            for (key, new_value) in new.iter().filter(|(key, _)| !old.contains_key(*key)) {
+                add_changes(&child_path(key), &Value::Null, new_value, changes);
+            }
+        },
+        (Value::Array(old), Value::Array(new)) => {
+            let mut old_items: Vec<String> = old.iter().map(Value::to_string).collect();
+            let mut new_items: Vec<String> = new.iter().map(Value::to_string).collect();
+            old_items.sort();
+            new_items.sort();
+            if old_items == new_items {
+                return;
+            }
+            let (old_text, new_text) = (Value::from(old.clone()).to_string(), Value::from(new.clone()).to_string());
+            if old_text.len() <= MAX_VALUE_LEN && new_text.len() <= MAX_VALUE_LEN {
+                changes.push(format!("{path}: {old_text} -> {new_text}"));
+                return;
+            }
+            let added = new_items.iter().filter(|item| !old_items.contains(item)).count();
+            let removed = old_items.iter().filter(|item| !new_items.contains(item)).count();
+            changes.push(format!("{path}: {added} added, {removed} removed"));
+        },
+        (old, new) if old != new => {
+            let (old_text, new_text) = (old.to_string(), new.to_string());
+            if old_text.len() <= MAX_VALUE_LEN && new_text.len() <= MAX_VALUE_LEN {
+                changes.push(format!("{path}: {old_text} -> {new_text}"));
+            }
+            else {
+                changes.push(format!("{path} changed"));
+            }
+        },
+        _ => {},
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::BTreeMap, thread, time::Duration};
+
+    use serde_json::json;
+
+    use crate::burrito::utils::TestDir;
+
+    use super::{get_changes, ConfigFile, ConfigWatcher};
+
+    #[test]
+    fn test_get_changes() {
+        let old = json!({
+            "log_dir": "/eve/logs",
+            "alert_config": {"coalesce_window_ms": 0, "rules": [{"name": "Hostiles within 5"}, {"name": "Faction spawn"}]},
+            "text_channels": ["Local", "delve.imperium"],
+            "character_tags": {},
+        });
+        let new = json!({
+            "log_dir": "/eve/logs",
+            "alert_config": {"coalesce_window_ms": 5000, "rules": [{"name": "Hostiles within 5"}, {"name": "Officer spawn"}, {"name": "Dread spawn"}]},
+            "text_channels": ["delve.imperium", "Local"],
+            "character_tags": {"Alt A": ["ratting"]},
+        });
+        assert_eq!(vec![
+            "alert_config.coalesce_window_ms: 0 -> 5000".to_owned(),
+            "alert_config.rules: 2 added, 1 removed".to_owned(),
+            "character_tags.Alt A: null -> [\"ratting\"]".to_owned(),
+        ], get_changes(&old, &new));
+        assert!(get_changes(&BTreeMap::from([("a", 1)]), &BTreeMap::from([("a", 1)])).is_empty());
+    }
+
+    #[test]
+    fn test_config_watcher() {
+        let dir = TestDir::new("reload");
+        std::fs::write(dir.join("burrito.cfg"), "{}").unwrap();
+        let mut uut = ConfigWatcher::new(dir.to_path_buf());
+        assert!(uut.get_changed().is_empty());
+
+        thread::sleep(Duration::from_millis(20));
+        std::fs::write(dir.join("burrito.cfg"), "{\"log_dir\": \"\"}").unwrap();
+        std::fs::write(dir.join("ctx.json"), "{}").unwrap();
+        // Changes are only reported once the files stay the same for a check
+        assert!(uut.get_changed().is_empty());
+        assert_eq!(vec![ConfigFile::Cfg, ConfigFile::Context], uut.get_changed());
+        assert!(uut.get_changed().is_empty());
+
+        // Files written by Burrito itself are not reloaded
+        thread::sleep(Duration::from_millis(20));
+        std::fs::write(dir.join("ctx.json"), "{\"current_characters\": []}").unwrap();
+        uut.set_loaded(ConfigFile::Context, super::get_modified(&dir.join("ctx.json")));
+        assert!(uut.get_changed().is_empty());
+        assert!(uut.get_changed().is_empty());
+    }
+}
@@ -1,6 +1,6 @@
 use std::{fs::File, io::{BufWriter, BufReader, Write}};
 
-use crate::burrito::utils;
+use crate::burrito::{cfg_check, utils};
 
 // TODO: Refactor bounds for T into a new trait
 pub fn read_or_create_default_data_struct<T: for<'a> serde::Deserialize<'a> + serde::Serialize + Default + Clone>(path: &str, filename: &str) -> T {
@@ -24,7 +24,9 @@ pub fn read_or_create_default_data_struct<T: for<'a> serde::Deserialize<'a> + se
     else {
         let f = File::open(&path_builder).expect("Unable to open data file");
         let reader = BufReader::new(f);
-        let loaded_struct: T = serde_json::from_reader(reader).expect("Invalid struct");
+        let loaded_struct: T = serde_json::from_reader(reader).unwrap_or_else(|e| {
+            panic!("Invalid {path_builder} at {}. Run `burrito cfg check` to list every problem", cfg_check::describe_json_error(&e))
+        });
         let mut f_w = std::fs::OpenOptions::new()
             .create(true)
             .write(true)
@@ -38,3 +40,13 @@ pub fn read_or_create_default_data_struct<T: for<'a> serde::Deserialize<'a> + se
     }
     return ret_value;
 }
+
+/// Overwrites a data file in the Burrito directory with `value`
+pub fn write_data_struct<T: serde::Serialize>(path: &str, filename: &str, value: &T) {
+    let mut path_builder = utils::get_burrito_dir();
+    path_builder.push_str(path);
+    path_builder.push_str(filename);
+    let f = File::create(&path_builder).expect("Could not create data file");
+    let writer = BufWriter::new(f);
+    serde_json::to_writer_pretty(writer, value).expect("Failed to write data file");
+}
@@ -1,5 +1,6 @@
 use std::collections::{HashMap, HashSet, VecDeque, BTreeMap};
 use std::io::Write;
+use std::time::SystemTime;
 use std::{fs::File, io::BufReader};
 
 use regex::Regex;
@@ -36,6 +37,9 @@ pub struct SystemContext {
     /// same systems will repeatedly be reported.
     #[serde(default)]
     path_cache: PathCache,
+    /// When ctx.json was last read or written by this context, to notice edits by others
+    #[serde(skip)]
+    modified: Option<SystemTime>,
 }
 
 impl SystemContext {
@@ -52,19 +56,37 @@ impl SystemContext {
         ctx
     }
 
-    fn save(&self) {
-        let mut path = setup_data_dir();
-        const CTX_FILE: &str = "/ctx.json";
-        path.push_str(CTX_FILE);
+    fn save(&mut self) {
+        self.write_to(&get_context_path());
+    }
+
+    fn write_to(&mut self, path: &str) {
         let mut f = std::fs::OpenOptions::new()
             .create(true)
             .write(true)
             .truncate(true)
-            .open(&path)
+            .open(path)
             .expect("Failed to save context file");
         f.write_all(serde_json::to_string(&self)
                 .expect("Failed to serialize context").as_bytes())
             .expect("Failed to write context to file");
+        self.modified = get_modified(path);
+    }
+
+    /// Saves the path cache, unless ctx.json was edited since this context last
+    /// read or wrote it
+    ///
+    /// The edit is kept so that it can be reloaded, instead of being replaced by
+    /// the watch lists of this context.
+    fn save_path_cache_to(&mut self, path: &str) {
+        if get_modified(path) == self.modified {
+            self.write_to(path);
+        }
+    }
+
+    /// When ctx.json was last read or written by this context
+    pub fn get_modified(&self) -> Option<SystemTime> {
+        self.modified
     }
 
     fn distances(&mut self, other_system: String, sys_map: &SystemMap) -> BTreeMap<Distance, SystemId> {
@@ -78,7 +100,6 @@ impl SystemContext {
             let other_sys_id = other_sys_id.unwrap();
             let key = (my_sys_id, other_sys_id);
             if let Some(path) = self.path_cache.search(&key) {
-                self.save();
                 results.insert(path, my_sys_id);
             }
             else {
@@ -87,25 +108,21 @@ impl SystemContext {
                     None => Distance::NoRoute,
                 };
                 self.path_cache.insert(key, computed_distance.clone());
-                self.save();
                 results.insert(computed_distance, my_sys_id);
             }
         }
+        if !results.is_empty() {
+            self.save_path_cache_to(&get_context_path());
+        }
         results
     }
 
     pub fn process_message(&mut self, message: String, sys_map: &SystemMap) -> BTreeMap<Distance, SystemId> {
-        for word in message.split(" ") {
-            let word = word.to_owned().replace("*", "");
-            if word.len() <= 2 {
-                continue;
-            }
-            if let Some(_) = get_system_id(&word, sys_map) {
-                return self.distances(
-                    word.to_owned(),
-                    sys_map
-                );
-            }
+        if let Some(system_name) = find_reported_system(&message, sys_map) {
+            return self.distances(
+                system_name,
+                sys_map
+            );
         }
         BTreeMap::new()
     }
@@ -122,13 +139,19 @@ impl SystemContext {
         &self.current_characters
     }
 
-    pub fn watch_system(&mut self, system_name: &str) {
+    pub fn watch_system(&mut self, system_name: &str, sys_map: &SystemMap) {
         self.current_systems.insert(system_name.to_owned());
+        if let Some(id) = get_system_id(system_name, sys_map) {
+            self.current_system_ids.insert(id);
+        }
         self.save();
     }
 
-    pub fn unwatch_system(&mut self, system_name: &str) {
+    pub fn unwatch_system(&mut self, system_name: &str, sys_map: &SystemMap) {
         self.current_systems.remove(system_name);
+        if let Some(id) = get_system_id(system_name, sys_map) {
+            self.current_system_ids.remove(&id);
+        }
         self.save();
     }
 
@@ -142,6 +165,46 @@ impl SystemContext {
         self.save();
     }
 
+    /// Takes over the watched systems and characters of a reloaded ctx.json,
+    /// keeping the path cache, and describes what changed
+    ///
+    /// Nothing changes if `saved` watches a system that does not exist.
+    pub fn set_watch_lists(&mut self, saved: &SystemContext, sys_map: &SystemMap) -> Result<Vec<String>, String> {
+        let mut system_ids = HashSet::new();
+        for system_name in &saved.current_systems {
+            let id = get_system_id(system_name, sys_map).ok_or(format!("Unknown system name: {system_name}"))?;
+            system_ids.insert(id);
+        }
+        let describe = |verb: &str, names: Vec<&String>| {
+            let mut names: Vec<&str> = names.into_iter().map(|name| name.as_str()).collect();
+            names.sort();
+            names.into_iter().map(|name| format!("{verb} {name}")).collect::<Vec<String>>()
+        };
+        let mut changes = describe("Watching system", saved.current_systems.difference(&self.current_systems).collect());
+        changes.extend(describe("No longer watching system", self.current_systems.difference(&saved.current_systems).collect()));
+        changes.extend(describe("Watching character", saved.current_characters.difference(&self.current_characters).collect()));
+        changes.extend(describe("No longer watching character", self.current_characters.difference(&saved.current_characters).collect()));
+        self.current_systems = saved.current_systems.clone();
+        self.current_system_ids = system_ids;
+        self.current_characters = saved.current_characters.clone();
+        self.modified = saved.modified;
+        Ok(changes)
+    }
+
+}
+
+/// Returns the first known system name in a chat message
+pub fn find_reported_system(message: &str, sys_map: &SystemMap) -> Option<String> {
+    for word in message.split(' ') {
+        let word = word.to_owned().replace('*', "");
+        if word.len() <= 2 {
+            continue;
+        }
+        if get_system_id(&word, sys_map).is_some() {
+            return Some(word);
+        }
+    }
+    None
 }
 
 pub fn get_system_id(sys_name: &str, sys_map: &SystemMap) -> Option<SystemId> {
@@ -175,12 +238,21 @@ impl SystemMap {
 
 }
 
-fn load_saved_context() -> SystemContext {
+fn get_context_path() -> String {
     let mut path = setup_data_dir();
     const CTX_FILE: &str = "/ctx.json";
+    path.push_str(CTX_FILE);
+    path
+}
+
+fn get_modified(path: &str) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|metadata| metadata.modified()).ok()
+}
+
+fn load_saved_context() -> SystemContext {
+    let path = get_context_path();
     let path_cache = Default::default();
     let mut ctx = SystemContext::default();
-    path.push_str(CTX_FILE);
     if !std::path::Path::new(&path).exists() {
         eprintln!("No saved context found. Creating default context");
         ctx.path_cache = path_cache;
@@ -196,6 +268,19 @@ fn load_saved_context() -> SystemContext {
     ctx
 }
 
+/// Reads ctx.json again while running, without creating or rewriting it
+pub fn read_saved_context() -> Result<SystemContext, String> {
+    read_context_from(&format!("{}ctx.json", utils::get_burrito_dir()))
+}
+
+fn read_context_from(path: &str) -> Result<SystemContext, String> {
+    let modified = get_modified(path);
+    let text = std::fs::read_to_string(path).map_err(|e| format!("Unable to read {path}: {e}"))?;
+    let mut ctx: SystemContext = serde_json::from_str(&text).map_err(|e| format!("Invalid {path}: {e}"))?;
+    ctx.modified = modified;
+    Ok(ctx)
+}
+
 pub fn load_saved_system_map() -> SystemMap {
     let mut path = setup_data_dir();
     const SYS_MAP_FILE: &str = "/systems.json";
@@ -458,4 +543,30 @@ mod tests {
         serde_json::from_reader(reader).unwrap()
     }
 
+    #[test]
+    fn test_path_cache_keeps_external_edits() {
+        use std::{thread, time::Duration};
+        use crate::burrito::utils::TestDir;
+        use super::{read_context_from, SystemContext};
+
+        let dir = TestDir::new("ctx");
+        let path = dir.get_path("ctx.json");
+        let mut uut = SystemContext::default();
+        uut.write_to(&path);
+
+        // Edited, e.g. by `burrito cfg watch`, while intel is coming in
+        thread::sleep(Duration::from_millis(20));
+        std::fs::write(&path, "{\"current_characters\": [\"Alt A\"]}").unwrap();
+        uut.save_path_cache_to(&path);
+        let saved = read_context_from(&path).unwrap();
+        assert!(saved.get_current_characters().contains("Alt A"));
+
+        // Once the edit is reloaded, the path cache is saved again
+        uut.set_watch_lists(&saved, &SystemMap::default()).unwrap();
+        thread::sleep(Duration::from_millis(20));
+        uut.save_path_cache_to(&path);
+        assert_eq!(uut.get_modified(), read_context_from(&path).unwrap().get_modified());
+        assert!(read_context_from(&path).unwrap().get_current_characters().contains("Alt A"));
+    }
+
 }
@@ -0,0 +1,127 @@
+use std::f32::consts::PI;
+
+use serde_derive::{Deserialize, Serialize};
+use strum_macros::{Display, EnumIter, EnumString};
+
+use super::log_watcher::EventKind;
+
+const SAMPLE_RATE: u32 = 44100;
+const AMPLITUDE: f32 = 0.4;
+/// Fade at the start and end of every segment to avoid clicks
+const FADE_MS: u32 = 5;
+
+/// Sounds that Burrito generates itself, so alerts are audible without any sound files
+#[derive(Clone, Copy, Debug, Deserialize, Display, EnumIter, EnumString, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize)]
+pub enum Tone {
+    /// Short mid-pitched beep
+    Beep,
+    /// Two short beeps
+    DoubleBeep,
+    /// Three quick high-pitched beeps
+    TripleBeep,
+    /// Long low-pitched beep
+    LowBeep,
+    /// Quick rising sweep
+    Chirp,
+    /// Slow rising and falling sweep, twice
+    Siren,
+    /// Alternating two-tone alarm
+    Alarm,
+}
+
+/// Part of a tone: a sweep from `start_hz` to `end_hz`, or silence if both are 0
+struct Segment {
+    start_hz: f32,
+    end_hz: f32,
+    duration_ms: u32,
+}
+
+fn tone(hz: f32, duration_ms: u32) -> Segment {
+    Segment { start_hz: hz, end_hz: hz, duration_ms }
+}
+
+fn sweep(start_hz: f32, end_hz: f32, duration_ms: u32) -> Segment {
+    Segment { start_hz, end_hz, duration_ms }
+}
+
+fn silence(duration_ms: u32) -> Segment {
+    Segment { start_hz: 0.0, end_hz: 0.0, duration_ms }
+}
+
+impl Tone {
+    /// Tone played for an event type when no sound file is available
+    pub fn for_event_kind(kind: &EventKind) -> Self {
+        match kind {
+            EventKind::RangeOfSystem | EventKind::RangeOfCharacter => Tone::DoubleBeep,
+            EventKind::SystemClear | EventKind::ChatConnectionRestored => Tone::Chirp,
+            EventKind::SystemStatusRequest | EventKind::ChatlogMessage | EventKind::GamelogMessage
+            | EventKind::SystemChangedMessage | EventKind::Bounty | EventKind::MiningYield => Tone::Beep,
+            EventKind::FactionSpawn => Tone::TripleBeep,
+            EventKind::DreadSpawn | EventKind::TitanSpawn | EventKind::OfficerSpawn => Tone::Siren,
+            EventKind::BountyTicksStopped | EventKind::OreHoldFull | EventKind::AsteroidDepleted => Tone::LowBeep,
+            EventKind::ChatConnectionLost | EventKind::DamageThreshold | EventKind::ClientInactive
+            | EventKind::SessionEnded => Tone::Alarm,
+        }
+    }
+
+    fn get_segments(&self) -> Vec<Segment> {
+        match self {
+            Tone::Beep => vec![tone(880.0, 150)],
+            Tone::DoubleBeep => vec![tone(880.0, 150), silence(100), tone(880.0, 150)],
+            Tone::TripleBeep => vec![tone(1320.0, 100), silence(80), tone(1320.0, 100), silence(80), tone(1320.0, 100)],
+            Tone::LowBeep => vec![tone(440.0, 400)],
+            Tone::Chirp => vec![sweep(600.0, 1800.0, 200)],
+            Tone::Siren => vec![sweep(600.0, 1200.0, 400), sweep(1200.0, 600.0, 400), sweep(600.0, 1200.0, 400), sweep(1200.0, 600.0, 400)],
+            Tone::Alarm => vec![tone(950.0, 200), tone(700.0, 200), tone(950.0, 200), tone(700.0, 200)],
+        }
+    }
+
+    /// Generates the tone as mono samples at `get_sample_rate()`
+    pub fn synthesize(&self) -> Vec<i16> {
+        let mut samples = vec![];
+        // Phase carries over between segments so sweeps join up smoothly
+        let mut phase = 0.0f32;
+        for segment in self.get_segments() {
+            let count = SAMPLE_RATE * segment.duration_ms / 1000;
+            let fade = (SAMPLE_RATE * FADE_MS / 1000).min(count / 2).max(1);
+            for i in 0..count {
+                if segment.start_hz == 0.0 && segment.end_hz == 0.0 {
+                    samples.push(0);
+                    continue;
+                }
+                let progress = i as f32 / count as f32;
+                let hz = segment.start_hz + (segment.end_hz - segment.start_hz) * progress;
+                phase = (phase + 2.0 * PI * hz / SAMPLE_RATE as f32) % (2.0 * PI);
+                let envelope = (i.min(count - 1 - i) as f32 / fade as f32).min(1.0);
+                samples.push((phase.sin() * envelope * AMPLITUDE * i16::MAX as f32) as i16);
+            }
+        }
+        samples
+    }
+
+    pub fn get_sample_rate() -> u32 {
+        SAMPLE_RATE
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use strum::IntoEnumIterator;
+
+    use super::Tone;
+
+    #[test]
+    fn test_synthesize() {
+        let samples = Tone::DoubleBeep.synthesize();
+        // 150 ms beep, 100 ms gap, 150 ms beep
+        assert_eq!(44100 * 400 / 1000, samples.len());
+        assert!(samples[..6615].iter().any(|s| *s > 10000));
+        assert!(samples[6615..11025].iter().all(|s| *s == 0));
+        for tone in Tone::iter() {
+            let samples = tone.synthesize();
+            assert!(!samples.is_empty(), "{tone} is empty");
+            // Fades in from silence
+            assert!(samples[0].abs() < 100, "{tone} starts with a click");
+        }
+    }
+}
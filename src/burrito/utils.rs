@@ -1,3 +1,7 @@
+use chrono::Duration;
+#[cfg(test)]
+use std::path::{Path, PathBuf};
+
 
 pub fn get_home_dir() -> String {
     let mut home_dir =
@@ -11,3 +15,63 @@ pub fn get_burrito_dir() -> String {
     s.push_str(".burrito/");
     s
 }
+
+/// Removes the `<color=...>`, `<b>` etc. markup the client writes into log lines
+pub fn strip_tags(text: &str) -> String {
+    let regex = regex::Regex::new(r"<[^>]*>").unwrap();
+    regex.replace_all(text, "").into_owned()
+}
+
+/// Parses a duration like `30m`, `2h` or `1h30m`
+pub fn parse_duration(text: &str) -> Option<Duration> {
+    if text.is_empty() {
+        return None;
+    }
+    let regex = regex::Regex::new(r"^(?:(\d+)h)?(?:(\d+)m(?:in)?)?(?:(\d+)s)?$").unwrap();
+    let captures = regex.captures(text)?;
+    let get = |i: usize| captures.get(i).and_then(|m| m.as_str().parse::<i64>().ok()).unwrap_or(0);
+    Some(Duration::hours(get(1)) + Duration::minutes(get(2)) + Duration::seconds(get(3)))
+}
+
+/// Empty directory for a test in the temp directory, deleted again when dropped
+#[cfg(test)]
+pub struct TestDir(PathBuf);
+
+#[cfg(test)]
+impl TestDir {
+    /// `name` must differ between tests, since they run in parallel
+    pub fn new(name: &str) -> Self {
+        let path = std::env::temp_dir().join(format!("burrito_test_{name}_{}", std::process::id()));
+        _ = std::fs::remove_dir_all(&path);
+        std::fs::create_dir_all(&path).unwrap();
+        Self(path)
+    }
+
+    /// Path of `name` in the directory as a string, e.g. for config values
+    pub fn get_path(&self, name: &str) -> String {
+        self.0.join(name).to_string_lossy().into_owned()
+    }
+}
+
+#[cfg(test)]
+impl std::ops::Deref for TestDir {
+    type Target = Path;
+
+    fn deref(&self) -> &Path {
+        &self.0
+    }
+}
+
+#[cfg(test)]
+impl AsRef<Path> for TestDir {
+    fn as_ref(&self) -> &Path {
+        &self.0
+    }
+}
+
+#[cfg(test)]
+impl Drop for TestDir {
+    fn drop(&mut self) {
+        _ = std::fs::remove_dir_all(&self.0);
+    }
+}
@@ -0,0 +1,249 @@
+use std::{collections::HashMap, sync::mpsc::{self, Sender}, thread, time::{Duration, Instant}};
+
+use super::{alert, alert_rules::AlertAction, diagnostics, log_watcher::LogEvent, notifier::Notifier};
+
+const REQUEST_TIMEOUT_MS: u64 = 10000;
+
+/// Posts alerts as JSON to webhooks such as Discord or Slack incoming webhooks
+///
+/// Requests are sent from a background thread for each URL, so a slow or failing
+/// endpoint neither holds up log processing nor the other webhooks. Failed
+/// requests are retried with exponential backoff, and each webhook action is
+/// rate limited separately.
+pub struct WebhookNotifier {
+    /// Payloads waiting to be sent, for each URL
+    queues: HashMap<String, Sender<String>>,
+    attempts: u32,
+    initial_backoff_ms: u64,
+    last_sent: HashMap<AlertAction, Instant>,
+}
+
+impl WebhookNotifier {
+    pub fn new() -> Self {
+        Self::with_retries(4, 1000)
+    }
+
+    /// Notifier that tries each request `attempts` times, waiting
+    /// `initial_backoff_ms` before the first retry and doubling the wait after each
+    pub fn with_retries(attempts: u32, initial_backoff_ms: u64) -> Self {
+        Self {
+            queues: HashMap::new(),
+            attempts,
+            initial_backoff_ms,
+            last_sent: HashMap::new(),
+        }
+    }
+
+    /// Queue of `url`, starting its thread on first use
+    fn get_queue(&mut self, url: &str) -> &Sender<String> {
+        let (attempts, initial_backoff_ms) = (self.attempts, self.initial_backoff_ms);
+        self.queues.entry(url.to_owned()).or_insert_with(|| {
+            let (sender, receiver) = mpsc::channel::<String>();
+            let url = url.to_owned();
+            thread::spawn(move || {
+                for payload in receiver {
+                    send_with_retries(&url, &payload, attempts, initial_backoff_ms);
+                }
+            });
+            sender
+        })
+    }
+}
+
+impl Default for WebhookNotifier {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Notifier for WebhookNotifier {
+    fn notify(&mut self, event: &LogEvent, action: &AlertAction) -> Result<(), String> {
+        if let AlertAction::Webhook { url, template, rate_limit_ms } = action {
+            if let Some(last_sent) = self.last_sent.get(action) {
+                if last_sent.elapsed() < Duration::from_millis(*rate_limit_ms) {
+                    return Ok(());
+                }
+            }
+            self.last_sent.insert(action.clone(), Instant::now());
+            let payload = render_payload(template.as_deref(), event);
+            self.get_queue(url).send(payload)
+                .map_err(|_| format!("Webhook thread for {url} is not running"))?;
+        }
+        Ok(())
+    }
+}
+
+fn send_with_retries(url: &str, payload: &str, attempts: u32, initial_backoff_ms: u64) {
+    let mut backoff_ms = initial_backoff_ms;
+    for attempt in 1..=attempts {
+        let result = ureq::post(url)
+            .timeout(Duration::from_millis(REQUEST_TIMEOUT_MS))
+            .set("Content-Type", "application/json")
+            .send_string(payload);
+        let retry = match result {
+            Ok(_) => return,
+            // Only rate limiting and server errors can succeed on a retry
+            Err(ureq::Error::Status(code, _)) => code == 429 || code >= 500,
+            Err(ureq::Error::Transport(_)) => true,
+        };
+        if !retry || attempt == attempts {
+            diagnostics::report(format!("Unable to send webhook to {}: {}", url, result.unwrap_err()));
+            return;
+        }
+        thread::sleep(Duration::from_millis(backoff_ms));
+        backoff_ms *= 2;
+    }
+}
+
+/// Builds the JSON body for an event
+///
+/// Without a template the body is the serialized `LogEvent` with the alert
+/// text added as `content` (Discord) and `text` (Slack). In a template,
+/// `{{event}}` is replaced by the serialized `LogEvent` and `{{<field>}}` by the
+/// JSON escaped value of a field from `alert::get_event_fields`. Placeholders
+/// are only replaced in the template itself, not in the values put into it.
+pub fn render_payload(template: Option<&str>, event: &LogEvent) -> String {
+    let fields = alert::get_event_fields(event);
+    let template = match template {
+        Some(template) => template,
+        None => {
+            let mut payload = serde_json::to_value(event).unwrap();
+            if let Some(object) = payload.as_object_mut() {
+                let text = alert::format_event(event);
+                object.insert("content".to_owned(), text.to_owned().into());
+                object.insert("text".to_owned(), text.into());
+            }
+            return payload.to_string();
+        },
+    };
+    let get_value = |name: &str| match name {
+        "event" => Some(serde_json::to_string(event).unwrap()),
+        name => fields.iter().find(|(field, _)| *field == name).map(|(_, value)| {
+            let escaped = serde_json::to_string(value).unwrap();
+            escaped[1..escaped.len() - 1].to_owned()
+        }),
+    };
+    let mut payload = String::new();
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        payload.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+        let placeholder = after.find("}}").and_then(|end| get_value(&after[..end]).map(|value| (value, end)));
+        match placeholder {
+            Some((value, end)) => {
+                payload.push_str(&value);
+                rest = &after[end + 2..];
+            },
+            None => {
+                payload.push_str("{{");
+                rest = after;
+            },
+        }
+    }
+    payload.push_str(rest);
+    payload
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{io::{BufRead, BufReader, Read, Write}, net::TcpListener, sync::mpsc::{self, Receiver}, thread, time::Duration};
+
+    use crate::burrito::{alert_rules::AlertAction, log_watcher::{EventType, LogEvent}, notifier::Notifier};
+
+    use super::{render_payload, WebhookNotifier};
+
+    fn event() -> LogEvent {
+        LogEvent::test(EventType::RangeOfSystem(2))
+            .with_trigger("Bad Guy \"Neut\" Jita")
+            .with_message("Bad Guy Neut is 2 jumps away")
+            .with_channel("east.imperium")
+    }
+
+    #[test]
+    fn test_render_payload() {
+        let payload: serde_json::Value = serde_json::from_str(&render_payload(None, &event())).unwrap();
+        assert_eq!("Alt A", payload["character_name"]);
+        assert_eq!("east.imperium", payload["channel"]);
+        assert_eq!("Bad Guy Neut is 2 jumps away", payload["content"]);
+
+        let template = r#"{"username": "Burrito", "content": "{{event_type}} {{distance}}: {{trigger}}", "event": {{event}}}"#;
+        let payload: serde_json::Value = serde_json::from_str(&render_payload(Some(template), &event())).unwrap();
+        assert_eq!("RangeOfSystem 2: Bad Guy \"Neut\" Jita", payload["content"]);
+        assert_eq!("Alt A", payload["event"]["character_name"]);
+    }
+
+    #[test]
+    fn test_render_payload_from_chat_text() {
+        let mut event = event();
+        event.message = "{{channel}} {{event}}".to_owned();
+        let template = r#"{"content": "{{text}}", "channel": "{{channel}}"}"#;
+        let payload: serde_json::Value = serde_json::from_str(&render_payload(Some(template), &event)).unwrap();
+        assert_eq!("{{channel}} {{event}}", payload["content"]);
+        assert_eq!("east.imperium", payload["channel"]);
+        assert_eq!("{{unknown}} {{text", render_payload(Some("{{unknown}} {{text"), &event));
+    }
+
+    /// Local stand-in for a webhook, answering with `get_status(i)` for the `i`th
+    /// request, and sending the bodies it receives
+    fn start_server(get_status: fn(usize) -> &'static str) -> (String, Receiver<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/webhook", listener.local_addr().unwrap());
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            for (i, stream) in listener.incoming().enumerate() {
+                let mut stream = stream.unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut content_length = 0;
+                loop {
+                    let mut line = String::new();
+                    reader.read_line(&mut line).unwrap();
+                    if line.trim().is_empty() {
+                        break;
+                    }
+                    if let Some(length) = line.to_lowercase().strip_prefix("content-length:") {
+                        content_length = length.trim().parse().unwrap();
+                    }
+                }
+                let mut body = vec![0; content_length];
+                reader.read_exact(&mut body).unwrap();
+                let status = get_status(i);
+                write!(stream, "HTTP/1.1 {status}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n").unwrap();
+                _ = sender.send(String::from_utf8(body).unwrap());
+            }
+        });
+        (url, receiver)
+    }
+
+    #[test]
+    fn test_webhook_retry_and_rate_limit() {
+        let (url, receiver) = start_server(|i| if i == 0 { "500 Internal Server Error" } else { "204 No Content" });
+
+        let action = AlertAction::Webhook { url, template: None, rate_limit_ms: 60000 };
+        let mut uut = WebhookNotifier::with_retries(3, 10);
+        assert_eq!(Ok(()), uut.notify(&event(), &action));
+        // Dropped by the rate limit
+        assert_eq!(Ok(()), uut.notify(&event(), &action));
+
+        let timeout = Duration::from_secs(5);
+        let first = receiver.recv_timeout(timeout).unwrap();
+        let retried = receiver.recv_timeout(timeout).unwrap();
+        assert_eq!(first, retried);
+        assert!(retried.contains("\"character_name\":\"Alt A\""));
+        assert!(receiver.recv_timeout(Duration::from_millis(200)).is_err());
+    }
+
+    #[test]
+    fn test_failing_webhook_does_not_delay_others() {
+        let (failing_url, _failing) = start_server(|_| "500 Internal Server Error");
+        let (url, receiver) = start_server(|_| "204 No Content");
+
+        let mut uut = WebhookNotifier::with_retries(3, 5000);
+        let failing = AlertAction::Webhook { url: failing_url, template: None, rate_limit_ms: 0 };
+        assert_eq!(Ok(()), uut.notify(&event(), &failing));
+        thread::sleep(Duration::from_millis(100));
+        let action = AlertAction::Webhook { url, template: None, rate_limit_ms: 0 };
+        assert_eq!(Ok(()), uut.notify(&event(), &action));
+        // The failing webhook waits 5 s before its next attempt
+        assert!(receiver.recv_timeout(Duration::from_secs(2)).is_ok());
+    }
+}